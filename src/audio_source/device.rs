@@ -0,0 +1,385 @@
+use anyhow::{anyhow, bail, Result};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{
+    Device, FromSample, Host, Sample, SampleFormat, SampleRate, SizedSample, Stream,
+    SupportedStreamConfig, SupportedStreamConfigRange,
+};
+use itertools::Itertools;
+
+use super::{mix_down, AudioSource};
+use crate::ring_buffer::Producer;
+
+// In Hz, plenty for a guitar. Higher rates only make the analysis slower, the first one the
+// device supports is picked
+const PREFERRED_SAMPLE_RATES: [u32; 2] = [48000, 44100];
+
+// Number of samples converted at once on the audio thread, the buffer is allocated beforehand
+const CONVERSION_SIZE: usize = 4096;
+
+// The input device to record, as picked on the command line
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DeviceInput {
+    // Name of the audio host, e.g. ALSA or JACK. The default host of the system when None
+    pub host: Option<String>,
+    // Name or index of the device, the default input device when None
+    pub device: Option<String>,
+    // The channel to tune from, starting at 0. All the channels are mixed down when None
+    pub channel: Option<u16>,
+}
+
+// Records an input device of the system, through cpal
+#[derive(Default)]
+pub struct DeviceSource {
+    input: DeviceInput,
+    // Name of the device, once started
+    name: Option<String>,
+    // Recording stops when the stream is dropped
+    stream: Option<Stream>,
+}
+
+// An input device, as listed by --list-devices
+pub struct DeviceInfo {
+    pub name: String,
+    pub is_default: bool,
+    pub configs: Vec<SupportedStreamConfigRange>,
+}
+
+// The input devices of an audio host, e.g. ALSA, CoreAudio or WASAPI
+pub struct HostDevices {
+    pub host: String,
+    pub devices: Result<Vec<DeviceInfo>>,
+}
+
+impl DeviceSource {
+    pub fn new(input: DeviceInput) -> Self {
+        Self {
+            input,
+            name: None,
+            stream: None,
+        }
+    }
+}
+
+impl AudioSource for DeviceSource {
+    // Fails if the device has no config to record from, e.g. not enough channels
+    fn start(&mut self, producer: Producer) -> Result<u32> {
+        let host = host(self.input.host.as_deref())?;
+        let channel = self.input.channel;
+
+        // Set up the input device and stream with the config that suits it best
+        let device = match self.input.device.as_ref() {
+            Some(selector) => find_device(&host, selector)?,
+            None => host
+                .default_input_device()
+                .ok_or(anyhow!("Can't find default input device"))?,
+        };
+        let name = device.name()?;
+
+        let config = get_device_input_config(&device, channel)
+            .map_err(|error| anyhow!("{}: {:#}", name, error))?;
+
+        let stream = match config.sample_format() {
+            SampleFormat::I8 => build_stream::<i8>(&device, &config, channel, producer),
+            SampleFormat::I16 => build_stream::<i16>(&device, &config, channel, producer),
+            SampleFormat::I32 => build_stream::<i32>(&device, &config, channel, producer),
+            SampleFormat::I64 => build_stream::<i64>(&device, &config, channel, producer),
+            SampleFormat::U8 => build_stream::<u8>(&device, &config, channel, producer),
+            SampleFormat::U16 => build_stream::<u16>(&device, &config, channel, producer),
+            SampleFormat::U32 => build_stream::<u32>(&device, &config, channel, producer),
+            SampleFormat::U64 => build_stream::<u64>(&device, &config, channel, producer),
+            SampleFormat::F32 => build_stream::<f32>(&device, &config, channel, producer),
+            SampleFormat::F64 => build_stream::<f64>(&device, &config, channel, producer),
+            format => bail!("{}: unsupported sample format {}", name, format),
+        }?;
+
+        stream.play()?;
+        self.name = Some(name);
+        self.stream = Some(stream);
+
+        Ok(config.sample_rate().0)
+    }
+
+    fn device(&self) -> Option<String> {
+        self.name.clone()
+    }
+}
+
+// Names of the input devices of the host, the tuner can switch to any of them
+pub fn input_device_names(host_name: Option<&str>) -> Result<Vec<String>> {
+    Ok(host(host_name)?
+        .input_devices()?
+        .filter_map(|device| device.name().ok())
+        .collect())
+}
+
+// Every host available on this system, with its input devices and their supported configs
+pub fn list_devices() -> Vec<HostDevices> {
+    cpal::available_hosts()
+        .into_iter()
+        .map(|host_id| {
+            let devices = cpal::host_from_id(host_id)
+                .map_err(|error| anyhow!(error))
+                .and_then(|host| {
+                    let default_name = host
+                        .default_input_device()
+                        .and_then(|device| device.name().ok());
+
+                    Ok(host
+                        .input_devices()?
+                        .map(|device| {
+                            let name = device.name().unwrap_or_else(|_| "Unknown".to_string());
+
+                            DeviceInfo {
+                                is_default: Some(&name) == default_name.as_ref(),
+                                configs: device
+                                    .supported_input_configs()
+                                    .map(|configs| configs.collect())
+                                    .unwrap_or_default(),
+                                name,
+                            }
+                        })
+                        .collect())
+                });
+
+            HostDevices {
+                host: host_id.name().to_string(),
+                devices,
+            }
+        })
+        .collect()
+}
+
+// The host is picked by name among the ones available at runtime. JACK is only there when the
+// jack feature is enabled
+fn host(name: Option<&str>) -> Result<Host> {
+    let name = match name {
+        Some(name) => name,
+        None => return Ok(cpal::default_host()),
+    };
+
+    let hosts = cpal::available_hosts();
+    match hosts
+        .iter()
+        .find(|host_id| host_id.name().eq_ignore_ascii_case(name))
+    {
+        Some(&host_id) => Ok(cpal::host_from_id(host_id)?),
+        None => bail!(
+            "Can't find audio host \"{}\", expected one of: {}",
+            name,
+            hosts.iter().map(|host_id| host_id.name()).join(", ")
+        ),
+    }
+}
+
+// The selector is either the index of the device, as listed by --list-devices, or its name
+// A part of the name is enough, as long as it matches a single device
+fn find_device(host: &Host, selector: &str) -> Result<Device> {
+    let devices: Vec<Device> = host.input_devices()?.collect();
+    let names: Vec<String> = devices
+        .iter()
+        .map(|device| device.name().unwrap_or_default())
+        .collect();
+
+    match select_device(&names, selector) {
+        Some(index) => Ok(devices.into_iter().nth(index).unwrap()),
+        None if names.is_empty() => bail!("There is no input device"),
+        None => bail!(
+            "Can't find input device \"{}\", expected an index or one of: {}",
+            selector,
+            names.join(", ")
+        ),
+    }
+}
+
+fn select_device(names: &[String], selector: &str) -> Option<usize> {
+    if let Ok(index) = selector.parse::<usize>() {
+        return (index < names.len()).then_some(index);
+    }
+
+    if let Some(index) = names.iter().position(|name| name == selector) {
+        return Some(index);
+    }
+
+    let selector = selector.to_lowercase();
+    let mut matches = names
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| name.to_lowercase().contains(&selector))
+        .map(|(index, _)| index);
+
+    match (matches.next(), matches.next()) {
+        (Some(index), None) => Some(index),
+        _ => None,
+    }
+}
+
+// Samples are converted to f64 and mixed down to mono before they're pushed, 32 bit integer
+// samples keep their full precision
+fn build_stream<T>(
+    device: &Device,
+    config: &SupportedStreamConfig,
+    channel: Option<u16>,
+    mut producer: Producer,
+) -> Result<Stream>
+where
+    T: SizedSample,
+    f64: FromSample<T>,
+{
+    let channels = config.channels();
+    let mut samples = Vec::with_capacity(CONVERSION_SIZE);
+
+    let err_fn = move |err| {
+        eprintln!("An error occurred on stream: {}", err);
+    };
+
+    // Runs on the real-time audio thread, it must not lock nor allocate
+    let stream = device.build_input_stream(
+        &config.config(),
+        move |data: &[T], _: &_| {
+            for chunk in data.chunks(CONVERSION_SIZE * channels as usize) {
+                samples.clear();
+                samples.extend(chunk.chunks_exact(channels as usize).map(|frame| {
+                    let frame = frame.iter().map(|&sample| f64::from_sample(sample));
+                    mix_down(frame, channels, channel)
+                }));
+                producer.push(&samples);
+            }
+        },
+        err_fn,
+        None,
+    )?;
+
+    Ok(stream)
+}
+
+fn get_device_input_config(device: &Device, channel: Option<u16>) -> Result<SupportedStreamConfig> {
+    let configs: Vec<SupportedStreamConfigRange> = device.supported_input_configs()?.collect();
+
+    choose_config(&configs, channel).ok_or_else(|| {
+        let max_channels = configs.iter().map(|config| config.channels()).max();
+
+        match (channel, max_channels) {
+            (Some(channel), Some(max_channels)) => anyhow!(
+                "No input channel {}, channels are numbered from 0 and there are {}",
+                channel,
+                max_channels
+            ),
+            _ => anyhow!("No supported input config"),
+        }
+    })
+}
+
+// The config with the fewest channels, the picked channel must be one of them, then the most
+// precise sample format
+fn choose_config(
+    configs: &[SupportedStreamConfigRange],
+    channel: Option<u16>,
+) -> Option<SupportedStreamConfig> {
+    let config = configs
+        .iter()
+        .filter(|config| config.channels() > channel.unwrap_or(0))
+        .max_by_key(|config| {
+            let preferred_rate = PREFERRED_SAMPLE_RATES.iter().any(|&rate| {
+                config.min_sample_rate().0 <= rate && rate <= config.max_sample_rate().0
+            });
+
+            (
+                preferred_rate,
+                u16::MAX - config.channels(),
+                format_precision(config.sample_format()),
+            )
+        })?;
+
+    Some(config.clone().with_sample_rate(sample_rate(config)))
+}
+
+fn sample_rate(config: &SupportedStreamConfigRange) -> SampleRate {
+    let (min, max) = (config.min_sample_rate().0, config.max_sample_rate().0);
+
+    let rate = PREFERRED_SAMPLE_RATES
+        .into_iter()
+        .find(|rate| (min..=max).contains(rate))
+        .unwrap_or(PREFERRED_SAMPLE_RATES[0].clamp(min, max));
+
+    SampleRate(rate)
+}
+
+fn format_precision(format: SampleFormat) -> u8 {
+    match format {
+        SampleFormat::F32 | SampleFormat::F64 => 3,
+        SampleFormat::I32 | SampleFormat::U32 | SampleFormat::I64 | SampleFormat::U64 => 2,
+        SampleFormat::I16 | SampleFormat::U16 => 1,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod device_tests {
+    use super::*;
+    use cpal::SupportedBufferSize;
+
+    #[test]
+    fn select_device_tests() {
+        let names = vec![
+            "default".to_string(),
+            "Scarlett 2i2 USB".to_string(),
+            "Built-in Microphone".to_string(),
+            "USB Audio CODEC".to_string(),
+        ];
+
+        assert_eq!(select_device(&names, "1"), Some(1));
+        assert_eq!(select_device(&names, "4"), None);
+        assert_eq!(select_device(&names, "default"), Some(0));
+        assert_eq!(select_device(&names, "scarlett"), Some(1));
+        assert_eq!(select_device(&names, "microphone"), Some(2));
+        // Both USB devices match
+        assert_eq!(select_device(&names, "usb"), None);
+        assert_eq!(select_device(&names, "Line in"), None);
+    }
+
+    fn config(
+        channels: u16,
+        min: u32,
+        max: u32,
+        format: SampleFormat,
+    ) -> SupportedStreamConfigRange {
+        SupportedStreamConfigRange::new(
+            channels,
+            SampleRate(min),
+            SampleRate(max),
+            SupportedBufferSize::Unknown,
+            format,
+        )
+    }
+
+    #[test]
+    fn choose_config_tests() {
+        // A stereo interface without float samples
+        let configs = vec![
+            config(2, 44100, 192000, SampleFormat::I16),
+            config(2, 44100, 192000, SampleFormat::I32),
+        ];
+        let chosen = choose_config(&configs, None).unwrap();
+        assert_eq!(chosen.channels(), 2);
+        assert_eq!(chosen.sample_format(), SampleFormat::I32);
+        assert_eq!(chosen.sample_rate(), SampleRate(48000));
+
+        // Mono is preferred, unless the channel isn't there
+        let configs = vec![
+            config(1, 8000, 44100, SampleFormat::I16),
+            config(2, 8000, 44100, SampleFormat::F32),
+        ];
+        let chosen = choose_config(&configs, None).unwrap();
+        assert_eq!(chosen.channels(), 1);
+        assert_eq!(chosen.sample_rate(), SampleRate(44100));
+        assert_eq!(choose_config(&configs, Some(1)).unwrap().channels(), 2);
+        assert_eq!(choose_config(&configs, Some(2)), None);
+
+        // A rate close to the preferred ones otherwise
+        let configs = vec![config(1, 96000, 192000, SampleFormat::F32)];
+        let chosen = choose_config(&configs, None).unwrap();
+        assert_eq!(chosen.sample_rate(), SampleRate(96000));
+
+        assert_eq!(choose_config(&[], None), None);
+    }
+}
@@ -0,0 +1,213 @@
+use anyhow::{bail, Context, Result};
+use hound::{SampleFormat, WavReader};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use super::{play_in_real_time, AudioSource, MAX_SAMPLE_RATE, MIN_SAMPLE_RATE};
+use crate::ring_buffer::Producer;
+use crate::FIXTURE_SAMPLE_RATE;
+
+// Plays samples in real time, as if they were recorded
+pub struct FileSource {
+    samples: Arc<[f64]>,
+    sample_rate: u32,
+    finished: Arc<AtomicBool>,
+}
+
+impl FileSource {
+    pub fn new(samples: &[f64], sample_rate: u32) -> Self {
+        Self {
+            samples: samples.into(),
+            sample_rate,
+            finished: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    // A WAV file, or a PCM file like the test fixtures
+    pub fn open(path: &Path) -> Result<Self> {
+        let (samples, sample_rate) = read_file(path)?;
+        Ok(Self::new(&samples, sample_rate))
+    }
+}
+
+// Read the samples of a recording, and its sample rate
+// WAV files can have any sample rate, bit depth and number of channels, the channels are mixed
+// down to mono. Other files are read as the test fixtures: one sample per line, at 44.1kHz
+// The sample rate of WAV files is checked against the same range as the raw PCM
+pub fn read_file(path: &Path) -> Result<(Vec<f64>, u32)> {
+    let is_wav = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("wav"));
+
+    if is_wav {
+        read_wav(path)
+    } else {
+        Ok((read_pcm(path)?, FIXTURE_SAMPLE_RATE))
+    }
+}
+
+fn read_wav(path: &Path) -> Result<(Vec<f64>, u32)> {
+    let reader =
+        WavReader::open(path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    decode_wav(reader).with_context(|| format!("{}: invalid WAV file", path.display()))
+}
+
+// The samples of a WAV stream mixed down to mono, and its sample rate
+fn decode_wav(reader: WavReader<impl Read>) -> Result<(Vec<f64>, u32)> {
+    let spec = reader.spec();
+
+    if !(MIN_SAMPLE_RATE..=MAX_SAMPLE_RATE).contains(&spec.sample_rate) {
+        bail!(
+            "sample rate must be between {} Hz and {} Hz, not {} Hz",
+            MIN_SAMPLE_RATE,
+            MAX_SAMPLE_RATE,
+            spec.sample_rate
+        );
+    }
+
+    let interleaved = match spec.sample_format {
+        SampleFormat::Float => reader
+            .into_samples::<f32>()
+            .map(|sample| sample.map(f64::from))
+            .collect::<Result<Vec<f64>, _>>()?,
+        SampleFormat::Int => {
+            // Full scale of a signed sample of that bit depth
+            let scale = (1_i64 << (spec.bits_per_sample - 1)) as f64;
+
+            reader
+                .into_samples::<i32>()
+                .map(|sample| sample.map(|sample| sample as f64 / scale))
+                .collect::<Result<Vec<f64>, _>>()?
+        }
+    };
+
+    let channels = spec.channels.max(1) as usize;
+    let samples = interleaved
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f64>() / channels as f64)
+        .collect();
+
+    Ok((samples, spec.sample_rate))
+}
+
+fn read_pcm(path: &Path) -> Result<Vec<f64>> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    content
+        .lines()
+        .enumerate()
+        .map(|(index, line)| {
+            line.trim().parse().with_context(|| {
+                format!("{}: invalid sample on line {}", path.display(), index + 1)
+            })
+        })
+        .collect()
+}
+
+impl AudioSource for FileSource {
+    fn start(&mut self, producer: Producer) -> Result<u32> {
+        let samples = self.samples.clone();
+        let mut position = 0;
+
+        play_in_real_time(
+            self.sample_rate,
+            producer,
+            self.finished.clone(),
+            move |chunk| {
+                let size = chunk.len().min(samples.len() - position);
+                chunk[..size].copy_from_slice(&samples[position..position + size]);
+                position += size;
+                size
+            },
+        );
+
+        Ok(self.sample_rate)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Acquire)
+    }
+}
+
+#[cfg(test)]
+mod file_tests {
+    use super::*;
+    use hound::{WavSpec, WavWriter};
+    use std::io::Cursor;
+
+    // Encode the samples to WAV in memory
+    fn wav(spec: WavSpec, samples: &[i32]) -> WavReader<Cursor<Vec<u8>>> {
+        let mut bytes = Cursor::new(vec![]);
+        let mut writer = WavWriter::new(&mut bytes, spec).unwrap();
+        for &sample in samples {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        bytes.set_position(0);
+        WavReader::new(bytes).unwrap()
+    }
+
+    // Encode the samples to WAV in memory and decode them back
+    fn round_trip(spec: WavSpec, samples: &[i32]) -> (Vec<f64>, u32) {
+        decode_wav(wav(spec, samples)).unwrap()
+    }
+
+    #[test]
+    fn reads_wav_of_any_bit_depth() {
+        for bits_per_sample in [8, 16, 24, 32] {
+            let spec = WavSpec {
+                channels: 1,
+                sample_rate: 48000,
+                bits_per_sample,
+                sample_format: SampleFormat::Int,
+            };
+            let full_scale: i64 = 1 << (bits_per_sample - 1);
+            let samples = [0, full_scale / 2, -full_scale].map(|sample| sample as i32);
+
+            let (samples, sample_rate) = round_trip(spec, &samples);
+            assert_eq!(sample_rate, 48000);
+            assert_eq!(samples, vec![0.0, 0.5, -1.0]);
+        }
+    }
+
+    #[test]
+    fn mixes_wav_channels_down() {
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 22050,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+
+        let (samples, sample_rate) = round_trip(spec, &[16384, 0, -16384, -16384]);
+        assert_eq!(sample_rate, 22050);
+        assert_eq!(samples, vec![0.25, -0.5]);
+    }
+
+    #[test]
+    fn rejects_wav_sample_rate_out_of_range() {
+        for sample_rate in [4000, 384000] {
+            let spec = WavSpec {
+                channels: 1,
+                sample_rate,
+                bits_per_sample: 16,
+                sample_format: SampleFormat::Int,
+            };
+
+            assert!(decode_wav(wav(spec, &[0, 0])).is_err(), "{}", sample_rate);
+        }
+    }
+
+    #[test]
+    fn reads_fixtures() {
+        let (samples, sample_rate) = read_file(Path::new("test/fixtures/E2_pcm")).unwrap();
+        assert_eq!(sample_rate, FIXTURE_SAMPLE_RATE);
+        assert!(!samples.is_empty());
+    }
+}
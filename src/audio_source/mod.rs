@@ -0,0 +1,183 @@
+use anyhow::Result;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::ring_buffer::Producer;
+use crate::FIXTURE_SAMPLE_RATE;
+
+pub use device::{
+    input_device_names, list_devices, DeviceInfo, DeviceInput, DeviceSource, HostDevices,
+};
+pub use file::{read_file, FileSource};
+pub use stdin::{parse_sample_rate, PcmFormat, RawPcm, StdinSource};
+pub use tone::ToneSource;
+
+mod device;
+mod file;
+mod stdin;
+mod tone;
+
+// In seconds, sources which aren't a device push their samples in chunks this long
+const CHUNK_DURATION: f64 = 0.01;
+
+// In Hz, range of the sample rate of the raw PCM and the WAV files
+const MIN_SAMPLE_RATE: u32 = 8000;
+const MAX_SAMPLE_RATE: u32 = 192000;
+
+// In Hz, range of the synthetic tone
+const MIN_TONE_FREQUENCY: f64 = 20.0;
+const MAX_TONE_FREQUENCY: f64 = 5000.0;
+
+// Where the samples come from: a sound card, a file, a generated tone...
+pub trait AudioSource {
+    // Start pushing samples to the producer, each one in the range of -1.0..1.0
+    // Returns the sample rate
+    fn start(&mut self, producer: Producer) -> Result<u32>;
+
+    // Whether all the samples were pushed. Only sources with an end, like files, get there
+    fn is_finished(&self) -> bool {
+        false
+    }
+
+    // Name of the input device, for sources recording one
+    fn device(&self) -> Option<String> {
+        None
+    }
+}
+
+// The source to record from, as picked on the command line
+// Sources are opened on the thread which reads their samples, a cpal stream can't be moved to
+// another thread
+#[derive(Clone, Debug, PartialEq)]
+pub enum Input {
+    Device(DeviceInput),
+    File(PathBuf),
+    // In Hz
+    Tone(f64),
+    // Raw PCM piped into stdin
+    Stdin(RawPcm),
+}
+
+impl Input {
+    pub fn open(&self) -> Result<Box<dyn AudioSource>> {
+        Ok(match self {
+            Input::Device(input) => Box::new(DeviceSource::new(input.clone())),
+            Input::File(path) => Box::new(FileSource::open(path)?),
+            Input::Tone(frequency) => Box::new(ToneSource::new(*frequency, FIXTURE_SAMPLE_RATE)),
+            Input::Stdin(pcm) => Box::new(StdinSource::new(*pcm)),
+        })
+    }
+}
+
+pub fn parse_tone_frequency(value: &str) -> Result<f64, String> {
+    let frequency: f64 = value
+        .parse()
+        .map_err(|_| format!("{} is not a frequency", value))?;
+
+    if !(MIN_TONE_FREQUENCY..=MAX_TONE_FREQUENCY).contains(&frequency) {
+        return Err(format!(
+            "tone frequency must be between {} Hz and {} Hz",
+            MIN_TONE_FREQUENCY, MAX_TONE_FREQUENCY
+        ));
+    }
+
+    Ok(frequency)
+}
+
+// A frame of interleaved samples to a mono sample: the picked channel, or the average of all the
+// channels
+fn mix_down(mut frame: impl Iterator<Item = f64>, channels: u16, channel: Option<u16>) -> f64 {
+    match channel {
+        Some(channel) => frame.nth(channel as usize).unwrap_or(0.0),
+        None => frame.sum::<f64>() / channels as f64,
+    }
+}
+
+// Push the samples at the pace of the sample rate, as a sound card would, from a background
+// thread. next_chunk fills the chunk and returns how many samples it wrote, 0 at the end
+// Stops once the samples are not read anymore
+fn play_in_real_time(
+    sample_rate: u32,
+    mut producer: Producer,
+    finished: Arc<AtomicBool>,
+    mut next_chunk: impl FnMut(&mut [f64]) -> usize + Send + 'static,
+) {
+    thread::spawn(move || {
+        let mut chunk = vec![0.0; (CHUNK_DURATION * sample_rate as f64).ceil() as usize];
+        let start = Instant::now();
+        let mut played = 0;
+
+        while !producer.is_abandoned() {
+            let size = next_chunk(&mut chunk);
+            if size == 0 {
+                break;
+            }
+
+            producer.push(&chunk[..size]);
+            played += size;
+
+            let deadline = start + Duration::from_secs_f64(played as f64 / sample_rate as f64);
+            thread::sleep(deadline.saturating_duration_since(Instant::now()));
+        }
+
+        finished.store(true, Ordering::Release);
+    });
+}
+
+#[cfg(test)]
+mod audio_source_tests {
+    use super::*;
+    use crate::ring_buffer::ring_buffer;
+
+    #[test]
+    fn file_is_played_in_real_time() {
+        let samples: Vec<f64> = (0..4410).map(|x| x as f64 / 4410.0).collect();
+        let mut source = FileSource::new(&samples, 44100);
+        let (producer, consumer) = ring_buffer(8192);
+
+        let start = Instant::now();
+        assert_eq!(source.start(producer).unwrap(), 44100);
+        while !source.is_finished() {
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        // 0.1 second of samples
+        assert!(start.elapsed() >= Duration::from_millis(90));
+
+        let mut result = vec![0.0; 4410];
+        assert_eq!(consumer.read_latest(&mut result), 4410);
+        for (played, sample) in result.iter().zip(samples) {
+            assert!((played - sample).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn tone_is_endless() {
+        let mut source = ToneSource::new(440.0, 44100);
+        let (producer, consumer) = ring_buffer(8192);
+
+        source.start(producer).unwrap();
+        thread::sleep(Duration::from_millis(50));
+
+        let mut result = vec![0.0; 441];
+        assert!(consumer.read_latest(&mut result) > 0);
+        assert!(!source.is_finished());
+        assert!(result.iter().any(|sample| sample.abs() > 0.1));
+    }
+
+    #[test]
+    fn open_missing_file() {
+        let input = Input::File(PathBuf::from("test/fixtures/missing_pcm"));
+        assert!(input.open().is_err());
+    }
+
+    #[test]
+    fn parse_tone_frequency_tests() {
+        assert_eq!(parse_tone_frequency("110"), Ok(110.0));
+        assert!(parse_tone_frequency("5").is_err());
+        assert!(parse_tone_frequency("A2").is_err());
+    }
+}
@@ -0,0 +1,315 @@
+use anyhow::{anyhow, bail, Error, Result};
+use std::fmt;
+use std::io::{self, Read};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use super::{mix_down, AudioSource, MAX_SAMPLE_RATE, MIN_SAMPLE_RATE};
+use crate::ring_buffer::Producer;
+
+// In bytes, how much is read from stdin at once
+const READ_SIZE: usize = 4096;
+
+// Encoding of the samples, always little-endian
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PcmFormat {
+    S16le,
+    S32le,
+    F32le,
+}
+
+impl fmt::Display for PcmFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format_string = match self {
+            PcmFormat::S16le => "s16le",
+            PcmFormat::S32le => "s32le",
+            PcmFormat::F32le => "f32le",
+        };
+
+        write!(f, "{}", format_string)
+    }
+}
+
+impl FromStr for PcmFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "s16le" | "s16_le" => Ok(PcmFormat::S16le),
+            "s32le" | "s32_le" => Ok(PcmFormat::S32le),
+            "f32le" | "float_le" => Ok(PcmFormat::F32le),
+            _ => Err(anyhow!(
+                "Invalid format, expected one of: s16le, s32le, f32le"
+            )),
+        }
+    }
+}
+
+impl PcmFormat {
+    fn bytes_per_sample(&self) -> usize {
+        match self {
+            PcmFormat::S16le => 2,
+            PcmFormat::S32le | PcmFormat::F32le => 4,
+        }
+    }
+
+    // Bytes of a single sample to a number in the range of -1.0..1.0
+    fn decode(&self, bytes: &[u8]) -> f64 {
+        match self {
+            PcmFormat::S16le => i16::from_le_bytes([bytes[0], bytes[1]]) as f64 / 32768.0,
+            PcmFormat::S32le => i32::from_le_bytes(bytes.try_into().unwrap()) as f64 / 2147483648.0,
+            PcmFormat::F32le => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        }
+    }
+}
+
+// Layout of the raw PCM stream, there is no header to tell it
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RawPcm {
+    pub format: PcmFormat,
+    pub sample_rate: u32,
+    // Number of interleaved channels
+    pub channels: u16,
+    // The channel to tune from, starting at 0. All the channels are mixed down when None
+    pub channel: Option<u16>,
+}
+
+impl RawPcm {
+    fn frame_size(&self) -> usize {
+        self.format.bytes_per_sample() * self.channels as usize
+    }
+
+    // Decode the complete frames of the bytes into mono samples
+    // Returns how many bytes were decoded, the rest is the start of the next frame
+    fn decode(&self, bytes: &[u8], samples: &mut Vec<f64>) -> usize {
+        let bytes_per_sample = self.format.bytes_per_sample();
+        let frames = bytes.chunks_exact(self.frame_size());
+        let decoded = frames.len() * self.frame_size();
+
+        samples.extend(frames.map(|frame| {
+            let frame = frame
+                .chunks_exact(bytes_per_sample)
+                .map(|sample| self.format.decode(sample));
+
+            mix_down(frame, self.channels, self.channel)
+        }));
+
+        decoded
+    }
+}
+
+// Raw PCM piped into stdin, e.g. from arecord, sox or pw-record
+// Samples are pushed as they arrive, the program writing them sets the pace
+pub struct StdinSource {
+    pcm: RawPcm,
+    finished: Arc<AtomicBool>,
+}
+
+impl StdinSource {
+    pub fn new(pcm: RawPcm) -> Self {
+        Self {
+            pcm,
+            finished: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+impl AudioSource for StdinSource {
+    fn start(&mut self, producer: Producer) -> Result<u32> {
+        if self.pcm.channels == 0 {
+            bail!("The PCM stream must have at least one channel");
+        }
+
+        if let Some(channel) = self
+            .pcm
+            .channel
+            .filter(|&channel| channel >= self.pcm.channels)
+        {
+            bail!(
+                "Channel {} doesn't exist, the PCM stream has {} channels numbered from 0",
+                channel,
+                self.pcm.channels
+            );
+        }
+
+        let pcm = self.pcm;
+        let finished = self.finished.clone();
+
+        thread::spawn(move || {
+            stream(io::stdin().lock(), pcm, producer);
+            finished.store(true, Ordering::Release);
+        });
+
+        Ok(self.pcm.sample_rate)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Acquire)
+    }
+}
+
+// Push the samples of the reader until its end, or until they are not read anymore
+fn stream(mut reader: impl Read, pcm: RawPcm, mut producer: Producer) {
+    let mut bytes = vec![0; READ_SIZE.max(2 * pcm.frame_size())];
+    // Bytes of a frame split between two reads
+    let mut pending = 0;
+    let mut samples = Vec::with_capacity(READ_SIZE);
+
+    while !producer.is_abandoned() {
+        let read = match reader.read(&mut bytes[pending..]) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => read,
+        };
+
+        let available = pending + read;
+        samples.clear();
+        let decoded = pcm.decode(&bytes[..available], &mut samples);
+        producer.push(&samples);
+
+        bytes.copy_within(decoded..available, 0);
+        pending = available - decoded;
+    }
+}
+
+pub fn parse_sample_rate(value: &str) -> Result<u32, String> {
+    let sample_rate: u32 = value
+        .parse()
+        .map_err(|_| format!("{} is not a sample rate", value))?;
+
+    if !(MIN_SAMPLE_RATE..=MAX_SAMPLE_RATE).contains(&sample_rate) {
+        return Err(format!(
+            "sample rate must be between {} Hz and {} Hz",
+            MIN_SAMPLE_RATE, MAX_SAMPLE_RATE
+        ));
+    }
+
+    Ok(sample_rate)
+}
+
+#[cfg(test)]
+mod stdin_tests {
+    use super::*;
+    use crate::ring_buffer::ring_buffer;
+
+    // Hands out the bytes a few at a time, splitting the frames
+    struct SlowReader(Vec<u8>);
+
+    impl Read for SlowReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let size = buf.len().min(self.0.len()).min(3);
+            buf[..size].copy_from_slice(&self.0[..size]);
+            self.0.drain(..size);
+            Ok(size)
+        }
+    }
+
+    fn pcm(format: PcmFormat, channels: u16, channel: Option<u16>) -> RawPcm {
+        RawPcm {
+            format,
+            sample_rate: 48000,
+            channels,
+            channel,
+        }
+    }
+
+    #[test]
+    fn decodes_formats() {
+        let mut samples = vec![];
+
+        let s16: Vec<u8> = [0_i16, 16384, -32768]
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect();
+        assert_eq!(pcm(PcmFormat::S16le, 1, None).decode(&s16, &mut samples), 6);
+        assert_eq!(samples, vec![0.0, 0.5, -1.0]);
+
+        samples.clear();
+        let s32: Vec<u8> = [i32::MIN, 1 << 30]
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect();
+        assert_eq!(pcm(PcmFormat::S32le, 1, None).decode(&s32, &mut samples), 8);
+        assert_eq!(samples, vec![-1.0, 0.5]);
+
+        samples.clear();
+        let f32: Vec<u8> = [0.25_f32, -0.75]
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect();
+        assert_eq!(pcm(PcmFormat::F32le, 1, None).decode(&f32, &mut samples), 8);
+        assert_eq!(samples, vec![0.25, -0.75]);
+    }
+
+    #[test]
+    fn picks_or_mixes_channels() {
+        let stereo: Vec<u8> = [16384_i16, -16384, 8192, 0]
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect();
+
+        let mut samples = vec![];
+        pcm(PcmFormat::S16le, 2, Some(1)).decode(&stereo, &mut samples);
+        assert_eq!(samples, vec![-0.5, 0.0]);
+
+        samples.clear();
+        pcm(PcmFormat::S16le, 2, None).decode(&stereo, &mut samples);
+        assert_eq!(samples, vec![0.0, 0.125]);
+    }
+
+    #[test]
+    fn keeps_incomplete_frames() {
+        let mut samples = vec![];
+        let decoded = pcm(PcmFormat::S16le, 2, None).decode(&[0, 64, 0, 64, 0, 64], &mut samples);
+
+        assert_eq!(decoded, 4);
+        assert_eq!(samples, vec![0.5]);
+    }
+
+    #[test]
+    fn streams_frames_split_between_reads() {
+        let bytes: Vec<u8> = (0..100_i32)
+            .flat_map(|x| [x << 16, -(x << 16)])
+            .flat_map(|x| x.to_le_bytes())
+            .collect();
+        let (producer, consumer) = ring_buffer(256);
+
+        stream(
+            SlowReader(bytes),
+            pcm(PcmFormat::S32le, 2, Some(0)),
+            producer,
+        );
+
+        let mut result = vec![0.0; 100];
+        assert_eq!(consumer.read_latest(&mut result), 100);
+        for (x, sample) in result.iter().enumerate() {
+            assert_eq!(*sample, x as f64 / 32768.0);
+        }
+    }
+
+    #[test]
+    fn missing_channel() {
+        let (producer, _consumer) = ring_buffer(256);
+        let mut source = StdinSource::new(pcm(PcmFormat::S16le, 2, Some(2)));
+
+        assert!(source.start(producer).is_err());
+    }
+
+    #[test]
+    fn parse_format() {
+        assert_eq!("s16le".parse::<PcmFormat>().unwrap(), PcmFormat::S16le);
+        assert_eq!("S32_LE".parse::<PcmFormat>().unwrap(), PcmFormat::S32le);
+        assert_eq!("f32le".parse::<PcmFormat>().unwrap(), PcmFormat::F32le);
+        assert!("u8".parse::<PcmFormat>().is_err());
+    }
+
+    #[test]
+    fn parse_sample_rate_tests() {
+        assert_eq!(parse_sample_rate("48000"), Ok(48000));
+        assert!(parse_sample_rate("0").is_err());
+        assert!(parse_sample_rate("50").is_err());
+        assert!(parse_sample_rate("384000").is_err());
+        assert!(parse_sample_rate("44.1k").is_err());
+    }
+}
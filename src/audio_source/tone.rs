@@ -0,0 +1,56 @@
+use anyhow::Result;
+use std::f64::consts::PI;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use super::{play_in_real_time, AudioSource};
+use crate::ring_buffer::Producer;
+
+// Relative amplitudes of the harmonics, a plucked string has more than a pure sine
+const HARMONICS: [f64; 4] = [1.0, 0.5, 0.3, 0.2];
+
+const AMPLITUDE: f64 = 0.3;
+
+// An endless tone at a fixed frequency
+pub struct ToneSource {
+    frequency: f64,
+    sample_rate: u32,
+}
+
+impl ToneSource {
+    pub fn new(frequency: f64, sample_rate: u32) -> Self {
+        Self {
+            frequency,
+            sample_rate,
+        }
+    }
+}
+
+impl AudioSource for ToneSource {
+    fn start(&mut self, producer: Producer) -> Result<u32> {
+        let step = 2.0 * PI * self.frequency / self.sample_rate as f64;
+        let mut phase: f64 = 0.0;
+
+        play_in_real_time(
+            self.sample_rate,
+            producer,
+            Arc::new(AtomicBool::new(false)),
+            move |chunk| {
+                for sample in chunk.iter_mut() {
+                    let value: f64 = HARMONICS
+                        .iter()
+                        .enumerate()
+                        .map(|(k, amplitude)| amplitude * ((k + 1) as f64 * phase).sin())
+                        .sum();
+
+                    *sample = AMPLITUDE * value;
+                    phase = (phase + step) % (2.0 * PI);
+                }
+
+                chunk.len()
+            },
+        );
+
+        Ok(self.sample_rate)
+    }
+}
@@ -0,0 +1,94 @@
+use anyhow::Result;
+use clap::Parser;
+use std::path::PathBuf;
+
+use terminal_guitar_tuner::audio_source::read_file;
+use terminal_guitar_tuner::detection::{analyse_samples, parse_hop, DEFAULT_HOP};
+use terminal_guitar_tuner::guitar::{parse_reference_pitch, A4_FREQUENCY};
+use terminal_guitar_tuner::instrument::Instrument;
+use terminal_guitar_tuner::pitch_detector::{
+    parse_gate_hysteresis, parse_gate_threshold, parse_hum_frequency, Algorithm, Config,
+};
+use terminal_guitar_tuner::AppEvent;
+
+#[derive(Parser, Debug)]
+#[command(about = "Print the notes detected in a recording, with their frequency and cents")]
+struct Args {
+    /// WAV file of any bit depth sampled between 8000 and 192000 Hz, or PCM file like the fixtures
+    path: PathBuf,
+
+    /// Time between two analysis windows, in milliseconds
+    #[arg(long, default_value_t = DEFAULT_HOP, value_parser = parse_hop)]
+    hop: u32,
+
+    /// Pitch detection algorithm: mpm, yin or hps
+    #[arg(long, default_value_t = Algorithm::McLeod)]
+    algorithm: Algorithm,
+
+    /// Frequency of A4 in Hz, between 400 and 480
+    #[arg(long, default_value_t = A4_FREQUENCY, value_parser = parse_reference_pitch)]
+    reference_pitch: f64,
+
+    /// Instrument: guitar, guitar7, guitar8, guitar12, bass, bass5, ukulele, mandolin or banjo
+    #[arg(long, default_value_t = Instrument::guitar().id.to_string())]
+    instrument: String,
+
+    /// Frequency of the mains hum to filter out, 50 or 60 Hz
+    #[arg(long, value_parser = parse_hum_frequency)]
+    hum_frequency: Option<f64>,
+
+    /// Noise gate threshold in dBFS, quieter input is considered silence
+    #[arg(
+        long,
+        default_value_t = Config::default().gate_threshold,
+        value_parser = parse_gate_threshold,
+        allow_negative_numbers = true
+    )]
+    gate_threshold: f64,
+
+    /// How many dB below the threshold the input must drop before it's considered silence again
+    #[arg(long, default_value_t = Config::default().gate_hysteresis, value_parser = parse_gate_hysteresis)]
+    gate_hysteresis: f64,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let instrument: Instrument = args.instrument.parse()?;
+    let config = Config {
+        reference_pitch: args.reference_pitch,
+        min_frequency: instrument.min_frequency,
+        max_frequency: instrument.max_frequency,
+        hum_frequency: args.hum_frequency,
+        gate_threshold: args.gate_threshold,
+        gate_hysteresis: args.gate_hysteresis,
+        ..Config::default()
+    };
+
+    let (samples, sample_rate) = read_file(&args.path)?;
+    let hop = (sample_rate as usize * args.hop as usize / 1000).max(1);
+    let timeline = analyse_samples(&samples, sample_rate, args.algorithm, &config, hop);
+
+    println!(
+        "{:>9}  {:<6} {:>10}  {:>7}",
+        "time", "note", "frequency", "cents"
+    );
+    for (time, event) in timeline {
+        let description = match event {
+            Some(AppEvent::PitchEstimated(estimate)) => format!(
+                "{:<6} {:>7.2} Hz  {:>+7.1}",
+                estimate.note.to_string(),
+                estimate.frequency,
+                estimate.cents
+            ),
+            Some(AppEvent::PluckDetected) => "pluck".to_string(),
+            Some(AppEvent::SilenceDetected(level)) => format!("silence ({:.1} dBFS)", level.rms),
+            Some(_) => "no pitch".to_string(),
+            // The attack of a pluck is skipped
+            None => "attack".to_string(),
+        };
+
+        println!("{:>8.3}s  {}", time, description);
+    }
+
+    Ok(())
+}
@@ -1,78 +1,238 @@
 use anyhow::Result;
+use clap::Parser;
 use std::env;
 use std::fs::OpenOptions;
-use std::io::Write;
+use std::path::PathBuf;
 use std::sync::mpsc;
-use std::thread;
-use std::time::{Duration, Instant};
 
-use terminal_guitar_tuner::guitar::Note;
-use terminal_guitar_tuner::pitch_detector;
-use terminal_guitar_tuner::recorder::Recorder;
+use terminal_guitar_tuner::audio_source::{
+    list_devices, parse_sample_rate, parse_tone_frequency, DeviceInput, Input, PcmFormat, RawPcm,
+};
+use terminal_guitar_tuner::detection;
+use terminal_guitar_tuner::guitar::{parse_reference_pitch, A4_FREQUENCY};
+use terminal_guitar_tuner::instrument::Instrument;
+use terminal_guitar_tuner::pitch_detector::{
+    parse_gate_hysteresis, parse_gate_threshold, parse_hum_frequency, parse_tolerance, Algorithm,
+    Config,
+};
+use terminal_guitar_tuner::scheduler::{parse_update_rate, DEFAULT_UPDATE_RATE};
+use terminal_guitar_tuner::tuning_library;
 use terminal_guitar_tuner::ui;
 use terminal_guitar_tuner::AppEvent;
+use terminal_guitar_tuner::FIXTURE_SAMPLE_RATE;
 
-const FRAME_RATE_PER_SECOND: u64 = 2;
+#[derive(Parser, Debug)]
+#[command(about = "A simple guitar tuner in your terminal")]
+struct Args {
+    /// Pitch detection algorithm: mpm, yin or hps
+    #[arg(long, default_value_t = Algorithm::McLeod)]
+    algorithm: Algorithm,
+
+    /// How far, in cents, a pitch can be from a string and still be detected as that string, up
+    /// to 600
+    #[arg(long, default_value_t = Config::default().tolerance, value_parser = parse_tolerance)]
+    tolerance: f64,
+
+    /// Frequency of A4 in Hz, between 400 and 480
+    #[arg(long, default_value_t = A4_FREQUENCY, value_parser = parse_reference_pitch)]
+    reference_pitch: f64,
+
+    /// Instrument: guitar, guitar7, guitar8, guitar12, bass, bass5, ukulele, mandolin or banjo
+    #[arg(long, default_value_t = Instrument::guitar().id.to_string())]
+    instrument: String,
+
+    /// Tuning name, either a preset of the instrument like "Drop D", "DADGAD" or "Open G", or a
+    /// tuning saved in the tuning library. Defaults to the standard tuning of the instrument
+    #[arg(long)]
+    tuning: Option<String>,
+
+    /// Frequency of the mains hum to filter out, 50 or 60 Hz. Useful with electric guitars
+    /// plugged into a cheap audio interface
+    #[arg(long, value_parser = parse_hum_frequency)]
+    hum_frequency: Option<f64>,
+
+    /// Noise gate threshold in dBFS, quieter input is considered silence
+    #[arg(
+        long,
+        default_value_t = Config::default().gate_threshold,
+        value_parser = parse_gate_threshold,
+        allow_negative_numbers = true
+    )]
+    gate_threshold: f64,
+
+    /// How many dB below the threshold the input must drop before it's considered silence again
+    #[arg(long, default_value_t = Config::default().gate_hysteresis, value_parser = parse_gate_hysteresis)]
+    gate_hysteresis: f64,
+
+    /// Number of pitch updates per second. The analysis windows overlap, a higher rate makes the
+    /// needle more responsive but uses more CPU
+    #[arg(long, default_value_t = DEFAULT_UPDATE_RATE, value_parser = parse_update_rate)]
+    update_rate: u32,
+
+    /// Audio host to record from, e.g. ALSA, or JACK when built with the jack feature. Defaults to
+    /// the default host of the system, --list-devices shows the available ones
+    #[arg(long, conflicts_with_all = ["file", "tone", "stdin"])]
+    host: Option<String>,
+
+    /// Input device to record, either its index or its name as listed by --list-devices. Defaults
+    /// to the default input device of the system
+    #[arg(long, conflicts_with_all = ["file", "tone", "stdin"])]
+    device: Option<String>,
+
+    /// Print the audio hosts and their input devices, with the configs they support
+    #[arg(long)]
+    list_devices: bool,
+
+    /// Tune from a recording instead of the microphone, played in real time. Either a WAV file
+    /// of any bit depth, sampled between 8000 and 192000 Hz, or a PCM file like the test fixtures
+    #[arg(long, conflicts_with_all = ["tone", "stdin"])]
+    file: Option<PathBuf>,
+
+    /// Tune from a synthetic tone at this frequency in Hz, to try the tuner without a guitar
+    #[arg(long, value_parser = parse_tone_frequency, conflicts_with = "stdin")]
+    tone: Option<f64>,
+
+    /// Tune from raw PCM piped into stdin, e.g. from arecord, sox or pw-record
+    #[arg(long)]
+    stdin: bool,
+
+    /// Sample format of the PCM piped into stdin: s16le, s32le or f32le
+    #[arg(long, default_value_t = PcmFormat::F32le, requires = "stdin")]
+    format: PcmFormat,
+
+    /// Sample rate of the PCM piped into stdin, in Hz
+    #[arg(
+        long,
+        default_value_t = FIXTURE_SAMPLE_RATE,
+        value_parser = parse_sample_rate,
+        requires = "stdin"
+    )]
+    rate: u32,
+
+    /// Number of interleaved channels of the PCM piped into stdin
+    #[arg(long, default_value_t = 1, requires = "stdin")]
+    channels: u16,
+
+    /// Channel to tune from, of the input device or of the PCM piped into stdin, starting at 0.
+    /// All the channels are mixed down by default
+    #[arg(long, conflicts_with_all = ["file", "tone"])]
+    channel: Option<u16>,
+}
+
+impl Args {
+    fn input(&self) -> Input {
+        if let Some(path) = self.file.as_ref() {
+            Input::File(path.clone())
+        } else if let Some(frequency) = self.tone {
+            Input::Tone(frequency)
+        } else if self.stdin {
+            Input::Stdin(RawPcm {
+                format: self.format,
+                sample_rate: self.rate,
+                channels: self.channels,
+                channel: self.channel,
+            })
+        } else {
+            Input::Device(DeviceInput {
+                host: self.host.clone(),
+                device: self.device.clone(),
+                channel: self.channel,
+            })
+        }
+    }
+}
 
 fn main() -> Result<()> {
+    let args = Args::parse();
+    if args.list_devices {
+        print_devices();
+        return Ok(());
+    }
+
+    let tuning_library_path = tuning_library::default_path();
+    let tuning_library = match tuning_library_path.as_ref() {
+        Some(path) => tuning_library::load(path)?,
+        None => vec![],
+    };
+    let instrument: Instrument = args.instrument.parse()?;
+    let tuning = match args.tuning.as_ref() {
+        Some(name) => tuning_library::find(name, &tuning_library, &instrument)?,
+        None => instrument.default_tuning().clone(),
+    };
     let (send, recv) = mpsc::channel::<AppEvent>();
+    let (config_send, config_recv) = mpsc::channel::<Config>();
+    let (device_send, device_recv) = mpsc::channel::<String>();
     let debug = env::var("DEBUG").is_ok();
 
-    thread::spawn(move || {
-        let tuning_notes = vec![
-            Note::new("E4"),
-            Note::new("B3"),
-            Note::new("G3"),
-            Note::new("D3"),
-            Note::new("A2"),
-            Note::new("E2"),
-        ];
-
-        let mut next_frame_deadline = Instant::now();
-        let buffer_size = 1 << 12;
-        let mut recorder = Recorder::new(buffer_size);
-        let sample_rate = recorder.record().unwrap();
-
-        // Open a file with append option
-        let mut debug_log_file = if debug {
+    let detector_config = Config {
+        tolerance: args.tolerance,
+        reference_pitch: args.reference_pitch,
+        min_frequency: instrument.min_frequency,
+        max_frequency: instrument.max_frequency,
+        hum_frequency: args.hum_frequency,
+        gate_threshold: args.gate_threshold,
+        gate_hysteresis: args.gate_hysteresis,
+    };
+
+    let options = detection::Options {
+        algorithm: args.algorithm,
+        config: detector_config,
+        update_rate: args.update_rate,
+        debug_log: if debug {
+            // Open a file with append option
             let file = OpenOptions::new()
                 .append(true)
                 .create(true)
-                .open("debug.log")
-                .unwrap();
+                .open("debug.log")?;
 
-            Some(file)
+            Some(Box::new(file))
         } else {
             None
-        };
+        },
+    };
+    detection::spawn(args.input(), options, send, config_recv, device_recv)?;
 
-        // Loop until interrupted by user
-        loop {
-            next_frame_deadline += Duration::from_millis(1000 / FRAME_RATE_PER_SECOND);
+    ui::render(
+        recv,
+        &instrument,
+        &tuning,
+        detector_config,
+        args.update_rate,
+        config_send,
+        device_send,
+        tuning_library_path,
+    )
+}
 
-            recorder.with_samples(|samples| {
-                let result = pitch_detector::detect_note(&samples, sample_rate.0, &tuning_notes);
+fn print_devices() {
+    for host in list_devices() {
+        println!("{}", host.host);
 
-                let event = match result {
-                    Ok((note, frequency)) => AppEvent::PitchDetected(note, frequency),
-                    Err(_) => AppEvent::NoPitchDetected,
-                };
+        let devices = match host.devices {
+            Ok(devices) => devices,
+            Err(error) => {
+                println!("  Unavailable: {:#}", error);
+                continue;
+            }
+        };
 
-                // Write to a file
-                match debug_log_file.as_mut() {
-                    Some(file) => {
-                        file.write(format!("{:?}\n", event).as_bytes()).unwrap();
-                    }
+        if devices.is_empty() {
+            println!("  No input device");
+        }
 
-                    None => (),
-                }
+        for (index, device) in devices.iter().enumerate() {
+            let default = if device.is_default { " (default)" } else { "" };
+            println!("  {}: {}{}", index, device.name, default);
 
-                let _ = send.send(event);
-                let _ = send.send(AppEvent::AudioRecorded(samples));
-                thread::sleep(next_frame_deadline - Instant::now());
-            });
+            for config in device.configs.iter() {
+                println!(
+                    "       {} channels, {} to {} Hz, {}",
+                    config.channels(),
+                    config.min_sample_rate().0,
+                    config.max_sample_rate().0,
+                    config.sample_format()
+                );
+            }
         }
-    });
-
-    ui::render(recv)
+    }
 }
@@ -1,56 +1,122 @@
 use anyhow::Result;
-use std::thread;
-use std::time::{Duration, Instant};
+use clap::Parser;
+use std::io;
+use std::sync::mpsc;
 
-use terminal_guitar_tuner::guitar::{get_note_frequency, Note};
-use terminal_guitar_tuner::pitch_detector;
-use terminal_guitar_tuner::recorder::Recorder;
+use terminal_guitar_tuner::audio_source::{DeviceInput, Input};
+use terminal_guitar_tuner::detection;
+use terminal_guitar_tuner::guitar::{get_note_frequency, parse_reference_pitch, A4_FREQUENCY};
+use terminal_guitar_tuner::instrument::Instrument;
+use terminal_guitar_tuner::pitch_detector::{
+    match_tuning_note, parse_gate_hysteresis, parse_gate_threshold, parse_hum_frequency,
+    parse_tolerance, Algorithm, Config,
+};
+use terminal_guitar_tuner::scheduler::{parse_update_rate, DEFAULT_UPDATE_RATE};
+use terminal_guitar_tuner::tuning_library;
 use terminal_guitar_tuner::AppEvent;
 
-const FRAME_RATE_PER_SECOND: u64 = 2;
+#[derive(Parser, Debug)]
+#[command(about = "Print the raw pitch detection events")]
+struct Args {
+    /// Pitch detection algorithm: mpm, yin or hps
+    #[arg(long, default_value_t = Algorithm::McLeod)]
+    algorithm: Algorithm,
+
+    /// How far, in cents, a pitch can be from a string and still be detected as that string, up
+    /// to 600
+    #[arg(long, default_value_t = Config::default().tolerance, value_parser = parse_tolerance)]
+    tolerance: f64,
+
+    /// Frequency of A4 in Hz, between 400 and 480
+    #[arg(long, default_value_t = A4_FREQUENCY, value_parser = parse_reference_pitch)]
+    reference_pitch: f64,
+
+    /// Instrument: guitar, guitar7, guitar8, guitar12, bass, bass5, ukulele, mandolin or banjo
+    #[arg(long, default_value_t = Instrument::guitar().id.to_string())]
+    instrument: String,
+
+    /// Tuning name, either a preset of the instrument like "Drop D", "DADGAD" or "Open G", or a
+    /// tuning saved in the tuning library. Defaults to the standard tuning of the instrument
+    #[arg(long)]
+    tuning: Option<String>,
+
+    /// Frequency of the mains hum to filter out, 50 or 60 Hz. Useful with electric guitars
+    /// plugged into a cheap audio interface
+    #[arg(long, value_parser = parse_hum_frequency)]
+    hum_frequency: Option<f64>,
+
+    /// Noise gate threshold in dBFS, quieter input is considered silence
+    #[arg(
+        long,
+        default_value_t = Config::default().gate_threshold,
+        value_parser = parse_gate_threshold,
+        allow_negative_numbers = true
+    )]
+    gate_threshold: f64,
+
+    /// How many dB below the threshold the input must drop before it's considered silence again
+    #[arg(long, default_value_t = Config::default().gate_hysteresis, value_parser = parse_gate_hysteresis)]
+    gate_hysteresis: f64,
+
+    /// Number of pitch updates per second. The analysis windows overlap, a higher rate makes the
+    /// needle more responsive but uses more CPU
+    #[arg(long, default_value_t = DEFAULT_UPDATE_RATE, value_parser = parse_update_rate)]
+    update_rate: u32,
+}
 
 fn main() -> Result<()> {
-    let join_handle = thread::spawn(move || {
-        let tuning_notes = vec![
-            Note::new("E4"),
-            Note::new("B3"),
-            Note::new("G3"),
-            Note::new("D3"),
-            Note::new("A2"),
-            Note::new("E2"),
-        ];
-
-        let mut next_frame_deadline = Instant::now();
-        let buffer_size = 1 << 11;
-        let mut recorder = Recorder::new(buffer_size);
-        let sample_rate = recorder.record().unwrap();
-
-        println!("Raw {:?}", sample_rate);
-
-        // Loop until interrupted by user
-        loop {
-            next_frame_deadline += Duration::from_millis(1000 / FRAME_RATE_PER_SECOND);
-
-            recorder.with_samples(|samples| {
-                let result = pitch_detector::detect_note(&samples, sample_rate.0, &tuning_notes);
-
-                let event = match result {
-                    Ok((note, frequency)) => AppEvent::PitchDetected(note, frequency),
-                    Err(_) => AppEvent::NoPitchDetected,
-                };
-
-                println!("{:?}", event);
-
-                if let AppEvent::PitchDetected(note, _) = event {
-                    let perfect_pitch = get_note_frequency(&note);
+    let args = Args::parse();
+    let tuning_library = match tuning_library::default_path() {
+        Some(path) => tuning_library::load(&path)?,
+        None => vec![],
+    };
+    let instrument: Instrument = args.instrument.parse()?;
+    let tuning = match args.tuning.as_ref() {
+        Some(name) => tuning_library::find(name, &tuning_library, &instrument)?,
+        None => instrument.default_tuning().clone(),
+    };
+
+    let detector_config = Config {
+        tolerance: args.tolerance,
+        reference_pitch: args.reference_pitch,
+        min_frequency: instrument.min_frequency,
+        max_frequency: instrument.max_frequency,
+        hum_frequency: args.hum_frequency,
+        gate_threshold: args.gate_threshold,
+        gate_hysteresis: args.gate_hysteresis,
+    };
+    let (send, recv) = mpsc::channel::<AppEvent>();
+    let (_config_send, config_recv) = mpsc::channel::<Config>();
+    let (_device_send, device_recv) = mpsc::channel::<String>();
+
+    // Events and frame timings are printed as they come
+    let options = detection::Options {
+        algorithm: args.algorithm,
+        config: detector_config,
+        update_rate: args.update_rate,
+        debug_log: Some(Box::new(io::stdout())),
+    };
+    let input = Input::Device(DeviceInput::default());
+    let sample_rate = detection::spawn(input, options, send, config_recv, device_recv)?;
+    println!("Raw sample rate {}", sample_rate);
+
+    // Loop until interrupted by user
+    for event in recv {
+        if let AppEvent::DeviceStarted(name, _) = &event {
+            println!("Recording from {}", name);
+        }
+
+        if let AppEvent::PitchEstimated(estimate) = event {
+            match match_tuning_note(&estimate, &tuning.notes, &detector_config) {
+                Some((note, frequency)) => {
+                    let perfect_pitch = get_note_frequency(&note, detector_config.reference_pitch);
+                    println!("{:?}", AppEvent::PitchDetected(note, frequency));
                     println!("Perfect pitch {:?}", perfect_pitch);
                 }
-
-                thread::sleep(next_frame_deadline - Instant::now());
-            });
+                None => println!("{:?}", AppEvent::NoPitchDetected),
+            }
         }
-    });
+    }
 
-    let _ = join_handle.join();
     Ok(())
 }
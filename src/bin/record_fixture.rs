@@ -5,16 +5,17 @@ use std::thread;
 use std::time::Duration;
 use terminal_guitar_tuner::FIXTURE_SAMPLE_RATE;
 
+use terminal_guitar_tuner::audio_source::{DeviceInput, DeviceSource};
 use terminal_guitar_tuner::recorder::Recorder;
 
 // Record into fixtures
 fn main() -> Result<()> {
     let mut recorder = Recorder::new(FIXTURE_SAMPLE_RATE as usize * 2);
-    recorder.record()?;
+    recorder.record(Box::new(DeviceSource::new(DeviceInput::default())))?;
 
     thread::sleep(Duration::from_millis(2500));
 
-    recorder.with_samples(|samples| {
+    recorder.with_samples(|samples, _| {
         let payload: String = samples
             .iter()
             .map(|&x| x.to_string())
@@ -27,7 +28,7 @@ fn main() -> Result<()> {
             .open("test/fixtures/D3_pcm")
             .unwrap();
 
-        file.write_all(format!("{}", payload).as_bytes()).unwrap();
+        file.write_all(payload.as_bytes()).unwrap();
     });
 
     Ok(())
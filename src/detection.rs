@@ -0,0 +1,386 @@
+use anyhow::{anyhow, Result};
+use std::io::Write;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Instant;
+
+use crate::audio_source::{input_device_names, DeviceInput, Input};
+use crate::pitch_detector::{
+    window_size, Algorithm, BandPassFilter, Config, Level, NoiseGate, OnsetDetector, PitchDetector,
+};
+use crate::recorder::Recorder;
+use crate::scheduler::FrameScheduler;
+use crate::{AppEvent, FIXTURE_SAMPLE_RATE};
+
+// In milliseconds, time between two windows of the offline analysis
+pub const DEFAULT_HOP: u32 = 40;
+const MAX_HOP: u32 = 1000;
+
+pub struct Options {
+    pub algorithm: Algorithm,
+    pub config: Config,
+    // Number of pitch updates per second
+    pub update_rate: u32,
+    // Events and frame timings are written there, in debug mode
+    pub debug_log: Option<Box<dyn Write + Send>>,
+}
+
+// Record from the input and send the detection events, on a background thread
+// Returns the sample rate once the input is recording. The thread stops at the end of the input,
+// or once nobody receives the events anymore
+// Devices picked from the UI are received from device_updates, by name
+pub fn spawn(
+    input: Input,
+    options: Options,
+    events: Sender<AppEvent>,
+    config_updates: Receiver<Config>,
+    device_updates: Receiver<String>,
+) -> Result<u32> {
+    let (ready_send, ready_recv) = mpsc::channel::<Result<u32>>();
+
+    thread::spawn(move || {
+        let (recorder, sample_rate) = match record(&input, &options.config) {
+            Ok(recording) => recording,
+            Err(error) => {
+                let _ = ready_send.send(Err(error));
+                return;
+            }
+        };
+
+        let _ = ready_send.send(Ok(sample_rate));
+
+        // Devices picked from the UI are recorded from the same host, on the same channel
+        let device_input = match input {
+            Input::Device(device_input) => Some(device_input),
+            _ => None,
+        };
+
+        run(
+            recorder,
+            device_input,
+            sample_rate,
+            options,
+            events,
+            config_updates,
+            device_updates,
+        );
+    });
+
+    ready_recv
+        .recv()
+        .map_err(|_| anyhow!("The detection thread stopped unexpectedly"))?
+}
+
+fn record(input: &Input, config: &Config) -> Result<(Recorder, u32)> {
+    let mut recorder = Recorder::new(window_size(FIXTURE_SAMPLE_RATE, config));
+    let sample_rate = recorder.record(input.open()?)?;
+
+    // The window must fit a few periods of the lowest note of the instrument
+    recorder.set_buffer_size(window_size(sample_rate, config));
+
+    Ok((recorder, sample_rate))
+}
+
+// Tell the UI which device is recorded, and which ones it can switch to
+// Returns false once nobody receives the events anymore
+fn announce_device(
+    recorder: &Recorder,
+    device_input: Option<&DeviceInput>,
+    events: &Sender<AppEvent>,
+) -> bool {
+    match (recorder.device(), device_input) {
+        (Some(name), Some(device_input)) => {
+            let host = device_input.host.as_deref();
+            let devices = input_device_names(host).unwrap_or_default();
+            events.send(AppEvent::DeviceStarted(name, devices)).is_ok()
+        }
+        _ => true,
+    }
+}
+
+// The analysis of successive frames, shared by the live detection and the offline analysis
+pub struct Analyser {
+    detector: Box<dyn PitchDetector + Send>,
+    sample_rate: u32,
+    filter: BandPassFilter,
+    gate: NoiseGate,
+    onset: OnsetDetector,
+}
+
+impl Analyser {
+    pub fn new(algorithm: Algorithm, sample_rate: u32, config: &Config) -> Self {
+        Self {
+            detector: algorithm.detector(),
+            sample_rate,
+            filter: BandPassFilter::new(sample_rate, config),
+            gate: NoiseGate::new(),
+            onset: OnsetDetector::new(sample_rate),
+        }
+    }
+
+    // Analyse the latest window of samples, of which `new_samples` at the end weren't seen yet
+    // Returns None during the attack of a pluck
+    pub fn analyse(
+        &mut self,
+        samples: &[f64],
+        new_samples: usize,
+        config: &Config,
+    ) -> Option<AppEvent> {
+        let filtered_samples = self.filter.process(samples, new_samples);
+        let level = Level::of(filtered_samples);
+        let plucked = self.onset.update(filtered_samples, new_samples, config);
+
+        // Room noise would come out as random notes, and the attack of a pluck is sharp.
+        // Nothing is sent until the attack is over
+        if plucked {
+            Some(AppEvent::PluckDetected)
+        } else if !self.gate.update(&level, config) {
+            Some(AppEvent::SilenceDetected(level))
+        } else {
+            self.onset.sustain(filtered_samples, config).map(|sustain| {
+                match self
+                    .detector
+                    .estimate_filtered_pitch(sustain, self.sample_rate, config)
+                {
+                    Ok(estimate) => AppEvent::PitchEstimated(estimate),
+                    Err(_) => AppEvent::NoPitchDetected,
+                }
+            })
+        }
+    }
+}
+
+// Analyse a whole recording, a window every `hop` samples, as fast as possible
+// Returns the time in seconds of each window with its event
+pub fn analyse_samples(
+    samples: &[f64],
+    sample_rate: u32,
+    algorithm: Algorithm,
+    config: &Config,
+    hop: usize,
+) -> Vec<(f64, Option<AppEvent>)> {
+    let mut analyser = Analyser::new(algorithm, sample_rate, config);
+    let mut window = vec![0.0; window_size(sample_rate, config)];
+
+    (hop..=samples.len())
+        .step_by(hop.max(1))
+        .map(|end| {
+            // Padded with zeros at the start, as the recorder does
+            let start = end.saturating_sub(window.len());
+            let padding = window.len() - (end - start);
+            window[..padding].fill(0.0);
+            window[padding..].copy_from_slice(&samples[start..end]);
+
+            let time = end as f64 / sample_rate as f64;
+            (time, analyser.analyse(&window, hop, config))
+        })
+        .collect()
+}
+
+fn run(
+    mut recorder: Recorder,
+    device_input: Option<DeviceInput>,
+    sample_rate: u32,
+    options: Options,
+    events: Sender<AppEvent>,
+    config_updates: Receiver<Config>,
+    device_updates: Receiver<String>,
+) {
+    let Options {
+        algorithm,
+        mut config,
+        update_rate,
+        mut debug_log,
+    } = options;
+    let mut analyser = Analyser::new(algorithm, sample_rate, &config);
+    let mut scheduler = FrameScheduler::new(update_rate);
+    let mut disconnected = !announce_device(&recorder, device_input.as_ref(), &events);
+
+    // Loop until the end of the input, or until the UI is closed
+    while !recorder.is_finished() && !disconnected {
+        let frame_start = Instant::now();
+
+        // Pick up the latest config changes made from the UI
+        if let Some(new_config) = config_updates.try_iter().last() {
+            config = new_config;
+        }
+
+        // The previous device keeps recording when the new one can't be
+        let device_update = device_updates.try_iter().last();
+        if let (Some(device), Some(device_input)) = (device_update, device_input.as_ref()) {
+            let input = Input::Device(DeviceInput {
+                device: Some(device),
+                ..device_input.clone()
+            });
+
+            match record(&input, &config) {
+                Ok((new_recorder, sample_rate)) => {
+                    recorder = new_recorder;
+                    analyser = Analyser::new(algorithm, sample_rate, &config);
+                    disconnected |= !announce_device(&recorder, Some(device_input), &events);
+                }
+                Err(error) => {
+                    let message = format!("{:#}", error);
+                    disconnected |= events.send(AppEvent::DeviceFailed(message)).is_err();
+                }
+            }
+        }
+
+        recorder.with_samples(|samples, new_samples| {
+            if let Some(event) = analyser.analyse(samples, new_samples, &config) {
+                if let Some(log) = debug_log.as_mut() {
+                    let _ = writeln!(log, "{:?}", event);
+                }
+
+                disconnected |= events.send(event).is_err();
+            }
+
+            disconnected |= events
+                .send(AppEvent::AudioRecorded(samples.to_vec()))
+                .is_err();
+        });
+
+        let analysis_time = frame_start.elapsed();
+        let timing = scheduler.wait();
+
+        if let Some(log) = debug_log.as_mut() {
+            let _ = writeln!(log, "Frame analysed in {:?}, {:?}", analysis_time, timing);
+        }
+    }
+}
+
+pub fn parse_hop(value: &str) -> Result<u32, String> {
+    let hop: u32 = value
+        .parse()
+        .map_err(|_| format!("{} is not a number of milliseconds", value))?;
+
+    if !(1..=MAX_HOP).contains(&hop) {
+        return Err(format!("hop must be between 1 and {} ms", MAX_HOP));
+    }
+
+    Ok(hop)
+}
+
+#[cfg(test)]
+mod detection_tests {
+    use super::*;
+    use crate::audio_source::read_file;
+    use crate::guitar::Note;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    fn options() -> Options {
+        Options {
+            algorithm: Algorithm::McLeod,
+            config: Config::default(),
+            update_rate: 25,
+            debug_log: None,
+        }
+    }
+
+    // The whole detection runs without a sound card
+    #[test]
+    fn detects_synthetic_tone() {
+        let (send, recv) = mpsc::channel();
+        let (_config_send, config_recv) = mpsc::channel();
+        let (_device_send, device_recv) = mpsc::channel();
+
+        let sample_rate = spawn(
+            Input::Tone(110.0),
+            options(),
+            send,
+            config_recv,
+            device_recv,
+        )
+        .unwrap();
+        assert_eq!(sample_rate, 44100);
+
+        let estimate = recv
+            .iter()
+            .find_map(|event| match event {
+                AppEvent::PitchEstimated(estimate) => Some(estimate),
+                _ => None,
+            })
+            .unwrap();
+
+        assert_eq!(estimate.note, Note::new("A2"));
+        assert!(estimate.cents.abs() < 5.0, "{}", estimate.cents);
+    }
+
+    #[test]
+    fn stops_at_the_end_of_the_file() {
+        let (send, recv) = mpsc::channel();
+        let (_config_send, config_recv) = mpsc::channel();
+        let (_device_send, device_recv) = mpsc::channel();
+        let input = Input::File(PathBuf::from("test/fixtures/E2_pcm"));
+
+        spawn(input, options(), send, config_recv, device_recv).unwrap();
+
+        let start = Instant::now();
+        let notes: Vec<Note> = recv
+            .iter()
+            .filter_map(|event| match event {
+                AppEvent::PitchEstimated(estimate) => Some(estimate.note),
+                _ => None,
+            })
+            .collect();
+
+        // The fixture lasts 2.5 seconds
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(notes.len() > 10);
+        assert!(
+            notes.iter().all(|note| *note == Note::new("E2")),
+            "{:?}",
+            notes
+        );
+    }
+
+    #[test]
+    fn reports_input_errors() {
+        let (send, _recv) = mpsc::channel();
+        let (_config_send, config_recv) = mpsc::channel();
+        let (_device_send, device_recv) = mpsc::channel();
+        let input = Input::File(PathBuf::from("test/fixtures/missing_pcm"));
+
+        assert!(spawn(input, options(), send, config_recv, device_recv).is_err());
+    }
+
+    #[test]
+    fn analyses_a_recording_offline() {
+        let (samples, sample_rate) = read_file(Path::new("test/fixtures/E2_pcm")).unwrap();
+        let timeline = analyse_samples(
+            &samples,
+            sample_rate,
+            Algorithm::McLeod,
+            &Config::default(),
+            2205,
+        );
+
+        // A window every 50ms
+        assert_eq!(timeline.len(), samples.len() / 2205);
+        assert_eq!(timeline[0].0, 0.05);
+
+        let notes: Vec<Note> = timeline
+            .iter()
+            .filter_map(|(_, event)| match event {
+                Some(AppEvent::PitchEstimated(estimate)) => Some(estimate.note),
+                _ => None,
+            })
+            .collect();
+
+        assert!(notes.len() > 10);
+        assert!(
+            notes.iter().all(|note| *note == Note::new("E2")),
+            "{:?}",
+            notes
+        );
+    }
+
+    #[test]
+    fn parse_hop_tests() {
+        assert_eq!(parse_hop("40"), Ok(40));
+        assert!(parse_hop("0").is_err());
+        assert!(parse_hop("2000").is_err());
+        assert!(parse_hop("fast").is_err());
+    }
+}
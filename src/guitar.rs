@@ -99,7 +99,9 @@ impl FromStr for Note {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let re = Regex::new(r"^([ABCDEFG])([#b]?)(\d)$").unwrap();
-        let captures = re.captures(s).unwrap();
+        let captures = re
+            .captures(s)
+            .ok_or_else(|| anyhow!("Invalid note {:?}, expected a note like E2 or C#4", s))?;
 
         let note: BaseNote = captures[1].parse()?;
         let accidentals = if captures[2].is_empty() {
@@ -110,61 +112,163 @@ impl FromStr for Note {
 
         let octave: u8 = captures[3].parse()?;
 
-        Ok(Self {
+        let note = Self {
             note,
             accidentals,
             octave,
-        })
+        };
+
+        let valid = !matches!(
+            note,
+            Note {
+                note: BaseNote::E | BaseNote::B,
+                accidentals: Some(Accidentals::Sharp),
+                ..
+            } | Note {
+                note: BaseNote::C | BaseNote::F,
+                accidentals: Some(Accidentals::Flat),
+                ..
+            }
+        );
+
+        if !valid {
+            return Err(anyhow!("Invalid note {:?}", s));
+        }
+
+        Ok(note)
     }
 }
 
 impl Note {
     pub fn new(string_representation: &str) -> Self {
-        let note = string_representation.parse().unwrap();
+        string_representation.parse().unwrap()
+    }
 
-        let valid = match note {
-            Note {
-                note: BaseNote::E,
-                accidentals: Some(Accidentals::Sharp),
-                ..
-            } => false,
+    // The plain ASCII notation accepted by the parser, e.g. C#4
+    pub fn notation(&self) -> String {
+        let accidentals = match self.accidentals {
+            Some(Accidentals::Sharp) => "#",
+            Some(Accidentals::Flat) => "b",
+            None => "",
+        };
 
-            Note {
-                note: BaseNote::B,
-                accidentals: Some(Accidentals::Sharp),
-                ..
-            } => false,
+        format!("{}{}{}", self.note, accidentals, self.octave)
+    }
+}
 
-            Note {
-                note: BaseNote::C,
-                accidentals: Some(Accidentals::Flat),
-                ..
-            } => false,
+// Notes of the built-in tunings, from the highest to the lowest string
+const TUNING_PRESETS: [(&str, [&str; 6]); 12] = [
+    ("Standard", ["E4", "B3", "G3", "D3", "A2", "E2"]),
+    ("Drop D", ["E4", "B3", "G3", "D3", "A2", "D2"]),
+    ("Drop C#", ["Eb4", "Bb3", "Gb3", "Db3", "Ab2", "Db2"]),
+    ("Drop C", ["D4", "A3", "F3", "C3", "G2", "C2"]),
+    ("Half step down", ["Eb4", "Bb3", "Gb3", "Db3", "Ab2", "Eb2"]),
+    ("Full step down", ["D4", "A3", "F3", "C3", "G2", "D2"]),
+    ("DADGAD", ["D4", "A3", "G3", "D3", "A2", "D2"]),
+    ("Open G", ["D4", "B3", "G3", "D3", "G2", "D2"]),
+    ("Open D", ["D4", "A3", "F#3", "D3", "A2", "D2"]),
+    ("Open E", ["E4", "B3", "G#3", "E3", "B2", "E2"]),
+    ("Open A", ["E4", "C#4", "A3", "E3", "A2", "E2"]),
+    ("Open C", ["E4", "C4", "G3", "C3", "G2", "C2"]),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tuning {
+    pub name: String,
+    // From the first to the last string, which goes from the highest to the lowest note
+    // except for re-entrant tunings
+    pub notes: Vec<Note>,
+}
 
-            Note {
-                note: BaseNote::F,
-                accidentals: Some(Accidentals::Flat),
-                ..
-            } => false,
+impl fmt::Display for Tuning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
 
-            _ => true,
-        };
+impl FromStr for Tuning {
+    type Err = Error;
 
-        if !valid {
-            panic!("Invalid note {:?}", note)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Tuning::presets()
+            .into_iter()
+            .find(|tuning| tuning.has_name(s))
+            .ok_or_else(|| {
+                let names = Tuning::presets().into_iter().map(|tuning| tuning.name);
+                anyhow!(
+                    "Invalid tuning, expected one of: {}",
+                    names.collect::<Vec<_>>().join(", ")
+                )
+            })
+    }
+}
+
+impl Tuning {
+    pub fn new(name: &str, notes: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            notes: notes.iter().map(|note| Note::new(note)).collect(),
         }
+    }
+
+    pub fn standard() -> Self {
+        Self::presets().remove(0)
+    }
+
+    pub fn presets() -> Vec<Self> {
+        TUNING_PRESETS
+            .iter()
+            .map(|(name, notes)| Self::new(name, notes))
+            .collect()
+    }
 
-        note
+    // Names are matched loosely, "Drop D", "drop-d" and "dropd" are the same tuning
+    pub fn has_name(&self, name: &str) -> bool {
+        normalize_tuning_name(&self.name) == normalize_tuning_name(name)
     }
 }
 
-const A4_FREQUENCY: f64 = 440.0;
+fn normalize_tuning_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric() || *c == '#')
+        .collect::<String>()
+        .to_lowercase()
+}
+
+// The standard reference pitch
+pub const A4_FREQUENCY: f64 = 440.0;
 
-pub fn get_note_frequency(note: &Note) -> f64 {
+// Reference pitches used in practice, including baroque and some orchestras, stay within this range
+pub const MIN_REFERENCE_PITCH: f64 = 400.0;
+pub const MAX_REFERENCE_PITCH: f64 = 480.0;
+
+// The reference pitch which makes a note sound at the given frequency
+pub fn reference_pitch_for(note: &Note, frequency: f64) -> f64 {
+    frequency * A4_FREQUENCY / get_note_frequency(note, A4_FREQUENCY)
+}
+
+// Parse a reference pitch given by the user, e.g. "442"
+pub fn parse_reference_pitch(value: &str) -> Result<f64, String> {
+    let reference_pitch: f64 = value
+        .parse()
+        .map_err(|_| format!("{} is not a frequency", value))?;
+
+    if !(MIN_REFERENCE_PITCH..=MAX_REFERENCE_PITCH).contains(&reference_pitch) {
+        return Err(format!(
+            "reference pitch must be between {} Hz and {} Hz",
+            MIN_REFERENCE_PITCH, MAX_REFERENCE_PITCH
+        ));
+    }
+
+    Ok(reference_pitch)
+}
+
+// Equal-tempered frequency of a note, with the reference_pitch as the frequency of A4
+pub fn get_note_frequency(note: &Note, reference_pitch: f64) -> f64 {
     let difference = semi_tone_count(note) - semi_tone_count(&Note::new("A4"));
 
     let exponent = difference as f64 / 12.0;
-    A4_FREQUENCY * 2_f64.powf(exponent)
+    reference_pitch * 2_f64.powf(exponent)
 }
 
 fn semi_tone_count(note: &Note) -> i8 {
@@ -187,6 +291,48 @@ fn semi_tone_count(note: &Note) -> i8 {
     count + (note.octave as i8) * 12
 }
 
+// Find the closest equal-tempered note to a frequency
+// Returns the note and how far the frequency is from it, in cents
+pub fn nearest_note(frequency: f64, reference_pitch: f64) -> (Note, f64) {
+    let a4_count = semi_tone_count(&Note::new("A4")) as f64;
+    let count = (12.0 * (frequency / reference_pitch).log2() + a4_count).round();
+
+    // Notes are limited to the C0-B9 range
+    let note = note_from_semi_tone_count(count.clamp(0.0, 119.0) as i8);
+    let cents = cents_difference(frequency, get_note_frequency(&note, reference_pitch));
+
+    (note, cents)
+}
+
+// How far a frequency is from the reference frequency, in cents. 100 cents is 1 semitone
+pub fn cents_difference(frequency: f64, reference: f64) -> f64 {
+    1200.0 * (frequency / reference).log2()
+}
+
+fn note_from_semi_tone_count(count: i8) -> Note {
+    let (note, accidentals) = match count % 12 {
+        0 => (BaseNote::C, None),
+        1 => (BaseNote::C, Some(Accidentals::Sharp)),
+        2 => (BaseNote::D, None),
+        3 => (BaseNote::D, Some(Accidentals::Sharp)),
+        4 => (BaseNote::E, None),
+        5 => (BaseNote::F, None),
+        6 => (BaseNote::F, Some(Accidentals::Sharp)),
+        7 => (BaseNote::G, None),
+        8 => (BaseNote::G, Some(Accidentals::Sharp)),
+        9 => (BaseNote::A, None),
+        10 => (BaseNote::A, Some(Accidentals::Sharp)),
+        11 => (BaseNote::B, None),
+        _ => unreachable!(),
+    };
+
+    Note {
+        note,
+        accidentals,
+        octave: (count / 12) as u8,
+    }
+}
+
 pub fn semi_tone_up(note: &Note) -> Note {
     match note {
         // E -> F
@@ -214,7 +360,7 @@ pub fn semi_tone_up(note: &Note) -> Note {
         Note {
             accidentals: None, ..
         } => {
-            let mut clone = note.clone();
+            let mut clone = *note;
             clone.accidentals = Some(Accidentals::Sharp);
 
             clone
@@ -225,7 +371,7 @@ pub fn semi_tone_up(note: &Note) -> Note {
             note: base_note,
             octave,
         } => {
-            let new_base_note = next_base_note(&base_note);
+            let new_base_note = next_base_note(base_note);
             let new_octave = if *base_note == BaseNote::B {
                 *octave + 1
             } else {
@@ -243,7 +389,7 @@ pub fn semi_tone_up(note: &Note) -> Note {
             accidentals: Some(Accidentals::Flat),
             ..
         } => {
-            let mut clone = note.clone();
+            let mut clone = *note;
             clone.accidentals = None;
 
             clone
@@ -276,7 +422,7 @@ pub fn semi_tone_down(note: &Note) -> Note {
         Note {
             accidentals: None, ..
         } => {
-            let mut clone = note.clone();
+            let mut clone = *note;
             clone.accidentals = Some(Accidentals::Flat);
 
             clone
@@ -287,7 +433,7 @@ pub fn semi_tone_down(note: &Note) -> Note {
             note: base_note,
             octave,
         } => {
-            let new_base_note = prev_base_note(&base_note);
+            let new_base_note = prev_base_note(base_note);
             let new_octave = if *base_note == BaseNote::C {
                 *octave - 1
             } else {
@@ -305,7 +451,7 @@ pub fn semi_tone_down(note: &Note) -> Note {
             accidentals: Some(Accidentals::Sharp),
             ..
         } => {
-            let mut clone = note.clone();
+            let mut clone = *note;
             clone.accidentals = None;
 
             clone
@@ -314,7 +460,7 @@ pub fn semi_tone_down(note: &Note) -> Note {
 }
 
 fn next_base_note(base_note: &BaseNote) -> BaseNote {
-    let notes = vec![
+    let notes = [
         BaseNote::A,
         BaseNote::B,
         BaseNote::C,
@@ -325,11 +471,11 @@ fn next_base_note(base_note: &BaseNote) -> BaseNote {
     ];
 
     let index = notes.iter().position(|n| n == base_note).unwrap();
-    notes[(index + 1) % notes.len()].clone()
+    notes[(index + 1) % notes.len()]
 }
 
 fn prev_base_note(base_note: &BaseNote) -> BaseNote {
-    let notes = vec![
+    let notes = [
         BaseNote::A,
         BaseNote::B,
         BaseNote::C,
@@ -340,7 +486,7 @@ fn prev_base_note(base_note: &BaseNote) -> BaseNote {
     ];
 
     let index = notes.iter().position(|n| n == base_note).unwrap();
-    notes[(index + notes.len() - 1) % notes.len()].clone()
+    notes[(index + notes.len() - 1) % notes.len()]
 }
 
 #[cfg(test)]
@@ -406,6 +552,20 @@ mod note_new {
     fn b4_can_not_sharp() {
         Note::new("B#4");
     }
+
+    #[test]
+    fn parse_invalid_note() {
+        assert!("H4".parse::<Note>().is_err());
+        assert!("E".parse::<Note>().is_err());
+        assert!("Fb2".parse::<Note>().is_err());
+    }
+
+    #[test]
+    fn notation() {
+        assert_eq!(Note::new("C#4").notation(), "C#4");
+        assert_eq!(Note::new("Bb3").notation(), "Bb3");
+        assert_eq!(Note::new("E2").notation(), "E2");
+    }
 }
 
 #[cfg(test)]
@@ -414,57 +574,195 @@ mod get_note_frequency_tests {
 
     #[test]
     fn a4() {
-        let result = get_note_frequency(&Note::new("A4"));
+        let result = get_note_frequency(&Note::new("A4"), A4_FREQUENCY);
         assert_eq!(result, A4_FREQUENCY);
     }
 
     #[test]
     fn a4_sharp() {
-        let result = get_note_frequency(&Note::new("A#4"));
+        let result = get_note_frequency(&Note::new("A#4"), A4_FREQUENCY);
         assert_eq!(result, 466.1637615180899);
     }
 
     #[test]
     fn c4() {
-        let result = get_note_frequency(&Note::new("C4"));
+        let result = get_note_frequency(&Note::new("C4"), A4_FREQUENCY);
         assert_eq!(result, 261.6255653005986);
     }
 
     #[test]
     fn b3_flat() {
-        let result = get_note_frequency(&Note::new("Bb3"));
+        let result = get_note_frequency(&Note::new("Bb3"), A4_FREQUENCY);
         assert_eq!(result, 233.08188075904496);
     }
 
     #[test]
     fn e4() {
-        let result = get_note_frequency(&Note::new("E4"));
+        let result = get_note_frequency(&Note::new("E4"), A4_FREQUENCY);
         assert_eq!(result, 329.6275569128699);
     }
 
     #[test]
     fn g3() {
-        let result = get_note_frequency(&Note::new("G3"));
+        let result = get_note_frequency(&Note::new("G3"), A4_FREQUENCY);
         assert_eq!(result, 195.99771799087463);
     }
 
     #[test]
     fn d3() {
-        let result = get_note_frequency(&Note::new("D3"));
+        let result = get_note_frequency(&Note::new("D3"), A4_FREQUENCY);
         assert_eq!(result, 146.8323839587038);
     }
 
     #[test]
     fn a2() {
-        let result = get_note_frequency(&Note::new("A2"));
+        let result = get_note_frequency(&Note::new("A2"), A4_FREQUENCY);
         assert_eq!(result, 110.0);
     }
 
     #[test]
     fn e2() {
-        let result = get_note_frequency(&Note::new("E2"));
+        let result = get_note_frequency(&Note::new("E2"), A4_FREQUENCY);
         assert_eq!(result, 82.4068892282175);
     }
+
+    #[test]
+    fn a4_with_442_reference() {
+        let result = get_note_frequency(&Note::new("A4"), 442.0);
+        assert_eq!(result, 442.0);
+    }
+
+    #[test]
+    fn e2_with_432_reference() {
+        let result = get_note_frequency(&Note::new("E2"), 432.0);
+        assert!((result - 80.9086).abs() < 1e-4);
+    }
+}
+
+#[cfg(test)]
+mod nearest_note_tests {
+    use super::*;
+
+    #[test]
+    fn exact_note() {
+        let (note, cents) = nearest_note(440.0, A4_FREQUENCY);
+
+        assert_eq!(note, Note::new("A4"));
+        assert_eq!(cents, 0.0);
+    }
+
+    #[test]
+    fn sharp_note() {
+        let (note, cents) = nearest_note(112.0, A4_FREQUENCY);
+
+        assert_eq!(note, Note::new("A2"));
+        assert!((cents - 31.194).abs() < 0.001);
+    }
+
+    #[test]
+    fn flat_note() {
+        let (note, cents) = nearest_note(162.0, A4_FREQUENCY);
+
+        assert_eq!(note, Note::new("E3"));
+        assert!((cents + 29.812).abs() < 0.001);
+    }
+
+    #[test]
+    fn accidental_note() {
+        let (note, _) = nearest_note(185.0, A4_FREQUENCY);
+        assert_eq!(note, Note::new("F#3"));
+    }
+
+    #[test]
+    fn out_of_range() {
+        let (note, cents) = nearest_note(10.0, A4_FREQUENCY);
+
+        assert_eq!(note, Note::new("C0"));
+        assert!(cents < -50.0);
+    }
+
+    #[test]
+    fn with_442_reference() {
+        let (note, cents) = nearest_note(440.0, 442.0);
+
+        assert_eq!(note, Note::new("A4"));
+        assert!((cents + 7.852).abs() < 0.001);
+    }
+}
+
+#[cfg(test)]
+mod tuning_tests {
+    use super::*;
+
+    #[test]
+    fn presets_are_valid() {
+        for tuning in Tuning::presets() {
+            assert_eq!(tuning.notes.len(), 6, "{}", tuning);
+        }
+    }
+
+    #[test]
+    fn parse_preset_name() {
+        let tuning: Tuning = "Drop D".parse().unwrap();
+
+        assert_eq!(tuning.name, "Drop D");
+        assert_eq!(tuning.notes[5], Note::new("D2"));
+    }
+
+    #[test]
+    fn parse_loose_preset_name() {
+        assert_eq!("drop-d".parse::<Tuning>().unwrap().name, "Drop D");
+        assert_eq!("dadgad".parse::<Tuning>().unwrap().name, "DADGAD");
+        assert_eq!(
+            "half_step_down".parse::<Tuning>().unwrap().name,
+            "Half step down"
+        );
+        assert_eq!("drop c#".parse::<Tuning>().unwrap().name, "Drop C#");
+    }
+
+    #[test]
+    fn parse_unknown_name() {
+        let result = "Drop Z".parse::<Tuning>();
+        assert!(result.unwrap_err().to_string().contains("Open G"));
+    }
+}
+
+#[cfg(test)]
+mod reference_pitch_for_tests {
+    use super::*;
+
+    #[test]
+    fn a4() {
+        assert_eq!(reference_pitch_for(&Note::new("A4"), 442.0), 442.0);
+    }
+
+    #[test]
+    fn other_note() {
+        let result = reference_pitch_for(&Note::new("E2"), 80.9086);
+        assert!((result - 432.0).abs() < 0.001);
+    }
+}
+
+#[cfg(test)]
+mod parse_reference_pitch_tests {
+    use super::*;
+
+    #[test]
+    fn valid_reference_pitch() {
+        assert_eq!(parse_reference_pitch("442"), Ok(442.0));
+        assert_eq!(parse_reference_pitch("431.5"), Ok(431.5));
+    }
+
+    #[test]
+    fn out_of_range_reference_pitch() {
+        assert!(parse_reference_pitch("220").is_err());
+        assert!(parse_reference_pitch("500").is_err());
+    }
+
+    #[test]
+    fn invalid_reference_pitch() {
+        assert!(parse_reference_pitch("A4").is_err());
+    }
 }
 
 #[cfg(test)]
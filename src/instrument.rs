@@ -0,0 +1,283 @@
+use anyhow::{anyhow, Error, Result};
+use core::fmt;
+use std::str::FromStr;
+
+use crate::guitar::{Note, Tuning};
+
+// Strings which are plucked together and tuned as one note
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Course {
+    Single,
+    // Two strings tuned to the same note
+    Unison,
+    // Two strings tuned an octave apart, the course note is the lower one
+    Octave,
+}
+
+impl Course {
+    pub fn string_count(&self) -> usize {
+        match self {
+            Course::Single => 1,
+            Course::Unison | Course::Octave => 2,
+        }
+    }
+}
+
+// Number of tuning pegs on each side of the headstock
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Headstock {
+    pub left: usize,
+    pub right: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instrument {
+    // Used to pick the instrument from the command line
+    pub id: &'static str,
+    pub name: &'static str,
+    // From the first to the last course, matching the notes of the tunings
+    pub courses: Vec<Course>,
+    pub headstock: Headstock,
+    // In Hz. The lowest open string to the highest overtones worth detecting
+    pub min_frequency: f64,
+    pub max_frequency: f64,
+    // The first one is the default tuning
+    pub tunings: Vec<Tuning>,
+}
+
+impl fmt::Display for Instrument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl FromStr for Instrument {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Instrument::all()
+            .into_iter()
+            .find(|instrument| instrument.id == s.to_lowercase())
+            .ok_or_else(|| {
+                let ids: Vec<&str> = Instrument::all().iter().map(|i| i.id).collect();
+                anyhow!("Invalid instrument, expected one of: {}", ids.join(", "))
+            })
+    }
+}
+
+impl Instrument {
+    pub fn all() -> Vec<Self> {
+        vec![
+            Self::guitar(),
+            Self {
+                id: "guitar7",
+                name: "7-string guitar",
+                courses: vec![Course::Single; 7],
+                headstock: Headstock { left: 4, right: 3 },
+                min_frequency: 45.0,
+                max_frequency: 1325.0,
+                tunings: vec![Tuning::new(
+                    "Standard",
+                    &["E4", "B3", "G3", "D3", "A2", "E2", "B1"],
+                )],
+            },
+            Self {
+                id: "guitar8",
+                name: "8-string guitar",
+                courses: vec![Course::Single; 8],
+                headstock: Headstock { left: 4, right: 4 },
+                min_frequency: 35.0,
+                max_frequency: 1325.0,
+                tunings: vec![Tuning::new(
+                    "Standard",
+                    &["E4", "B3", "G3", "D3", "A2", "E2", "B1", "F#1"],
+                )],
+            },
+            Self {
+                id: "guitar12",
+                name: "12-string guitar",
+                // The lower four courses have an octave string
+                courses: vec![
+                    Course::Unison,
+                    Course::Unison,
+                    Course::Octave,
+                    Course::Octave,
+                    Course::Octave,
+                    Course::Octave,
+                ],
+                headstock: Headstock { left: 6, right: 6 },
+                min_frequency: 60.0,
+                max_frequency: 1325.0,
+                tunings: vec![Tuning::standard()],
+            },
+            Self {
+                id: "bass",
+                name: "Bass",
+                courses: vec![Course::Single; 4],
+                headstock: Headstock { left: 2, right: 2 },
+                min_frequency: 30.0,
+                max_frequency: 800.0,
+                tunings: vec![
+                    Tuning::new("Standard", &["G2", "D2", "A1", "E1"]),
+                    Tuning::new("Drop D", &["G2", "D2", "A1", "D1"]),
+                ],
+            },
+            Self {
+                id: "bass5",
+                name: "5-string bass",
+                courses: vec![Course::Single; 5],
+                headstock: Headstock { left: 2, right: 3 },
+                min_frequency: 25.0,
+                max_frequency: 800.0,
+                tunings: vec![Tuning::new("Standard", &["G2", "D2", "A1", "E1", "B0"])],
+            },
+            // Re-entrant tuning, the 4th string is higher than the 3rd one
+            Self {
+                id: "ukulele",
+                name: "Ukulele",
+                courses: vec![Course::Single; 4],
+                headstock: Headstock { left: 2, right: 2 },
+                min_frequency: 180.0,
+                max_frequency: 1800.0,
+                tunings: vec![
+                    Tuning::new("Standard", &["A4", "E4", "C4", "G4"]),
+                    Tuning::new("Low G", &["A4", "E4", "C4", "G3"]),
+                    Tuning::new("D tuning", &["B4", "F#4", "D4", "A4"]),
+                ],
+            },
+            Self {
+                id: "mandolin",
+                name: "Mandolin",
+                courses: vec![Course::Unison; 4],
+                headstock: Headstock { left: 4, right: 4 },
+                min_frequency: 150.0,
+                max_frequency: 2700.0,
+                tunings: vec![Tuning::new("Standard", &["E5", "A4", "D4", "G3"])],
+            },
+            // The short 5th string is re-entrant as well
+            Self {
+                id: "banjo",
+                name: "Banjo",
+                courses: vec![Course::Single; 5],
+                headstock: Headstock { left: 2, right: 3 },
+                min_frequency: 120.0,
+                max_frequency: 1800.0,
+                tunings: vec![
+                    Tuning::new("Open G", &["D4", "B3", "G3", "D3", "G4"]),
+                    Tuning::new("Double C", &["D4", "C4", "G3", "C3", "G4"]),
+                ],
+            },
+        ]
+    }
+
+    pub fn guitar() -> Self {
+        Self {
+            id: "guitar",
+            name: "Guitar",
+            courses: vec![Course::Single; 6],
+            headstock: Headstock { left: 3, right: 3 },
+            min_frequency: 60.0,
+            max_frequency: 1325.0,
+            tunings: Tuning::presets(),
+        }
+    }
+
+    pub fn default_tuning(&self) -> &Tuning {
+        &self.tunings[0]
+    }
+
+    pub fn string_count(&self) -> usize {
+        self.courses
+            .iter()
+            .map(|course| course.string_count())
+            .sum()
+    }
+
+    // The built-in tuning of the instrument with exactly these notes, if any
+    pub fn tuning_of(&self, notes: &[Note]) -> Option<&Tuning> {
+        self.tunings.iter().find(|tuning| tuning.notes == notes)
+    }
+
+    // Each string has its own peg, the strings of a course are next to each other
+    pub fn pegs_of_course(&self, course_index: usize) -> Vec<usize> {
+        let first_peg: usize = self.courses[..course_index]
+            .iter()
+            .map(|course| course.string_count())
+            .sum();
+
+        (first_peg..first_peg + self.courses[course_index].string_count()).collect()
+    }
+
+    // Make sure the tuning can be played on the instrument
+    pub fn check_tuning(&self, tuning: &Tuning) -> Result<()> {
+        if tuning.notes.len() != self.courses.len() {
+            return Err(anyhow!(
+                "Tuning {:?} has {} notes but the {} has {} courses",
+                tuning.name,
+                tuning.notes.len(),
+                self.name.to_lowercase(),
+                self.courses.len()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod instrument_tests {
+    use super::*;
+
+    #[test]
+    fn tunings_match_courses() {
+        for instrument in Instrument::all() {
+            for tuning in instrument.tunings.iter() {
+                assert!(instrument.check_tuning(tuning).is_ok(), "{}", tuning);
+            }
+        }
+    }
+
+    #[test]
+    fn pegs_match_headstock() {
+        for instrument in Instrument::all() {
+            let headstock = instrument.headstock;
+            assert_eq!(
+                headstock.left + headstock.right,
+                instrument.string_count(),
+                "{}",
+                instrument
+            );
+        }
+    }
+
+    #[test]
+    fn parse_instrument() {
+        assert_eq!("bass5".parse::<Instrument>().unwrap().name, "5-string bass");
+        assert_eq!("Ukulele".parse::<Instrument>().unwrap().id, "ukulele");
+        assert!("theremin".parse::<Instrument>().is_err());
+    }
+
+    #[test]
+    fn twelve_string_courses() {
+        let instrument: Instrument = "guitar12".parse().unwrap();
+
+        assert_eq!(instrument.string_count(), 12);
+        assert_eq!(instrument.pegs_of_course(0), vec![0, 1]);
+        assert_eq!(instrument.pegs_of_course(5), vec![10, 11]);
+    }
+
+    #[test]
+    fn tuning_of() {
+        let bass: Instrument = "bass".parse().unwrap();
+        let notes = bass.tunings[1].notes.clone();
+
+        assert_eq!(bass.tuning_of(&notes).unwrap().name, "Drop D");
+        assert_eq!(Instrument::guitar().tuning_of(&notes), None);
+    }
+
+    #[test]
+    fn check_tuning() {
+        let result = Instrument::guitar().check_tuning(&Tuning::new("Bass", &["G2", "D2"]));
+        assert!(result.is_err());
+    }
+}
@@ -2,22 +2,47 @@
 extern crate lazy_static;
 
 use crate::guitar::Note;
+use crate::pitch_detector::{Level, PitchEstimate};
 
+pub mod audio_source;
+pub mod detection;
 pub mod guitar;
+pub mod instrument;
 pub mod pitch_detector;
 pub mod recorder;
+pub mod ring_buffer;
+pub mod scheduler;
+pub mod tuning_library;
 pub mod ui;
 
 #[derive(Debug)]
 pub enum AppEvent {
     PitchDetected(Note, f64),
+    PitchEstimated(PitchEstimate),
     NoPitchDetected,
+    // The input is below the noise gate
+    SilenceDetected(Level),
+    // A string was plucked, its pitch is measured once the attack is over
+    PluckDetected,
     AudioRecorded(Vec<f64>),
+    // Recording from the named device, with the names of the input devices to switch to
+    DeviceStarted(String, Vec<String>),
+    // The chosen device couldn't be recorded, the previous one still is
+    DeviceFailed(String),
     DownButtonPressed,
     UpButtonPressed,
     LeftButtonPressed,
     RightButtonPressed,
     EscButtonPressed,
+    ChromaticButtonPressed,
+    CalibrationButtonPressed,
+    PlusButtonPressed,
+    MinusButtonPressed,
+    SaveButtonPressed,
+    DeviceButtonPressed,
+    CharacterTyped(char),
+    BackspacePressed,
+    EnterPressed,
     Quit,
 }
 
@@ -0,0 +1,277 @@
+use std::f64::consts::PI;
+
+use super::Config;
+
+// Mains hum isn't a pure sine, rectifiers and transformers add the first few harmonics
+const HUM_HARMONICS: usize = 5;
+
+// In Hz, width of the hum notches. Narrow enough to keep the strings close to a hum harmonic,
+// like G3 at 196 Hz next to 200 Hz. The hum fades out of the notches within a second
+const NOTCH_BANDWIDTH: f64 = 2.0;
+
+// Quality factors of the two sections of a 4th order Butterworth filter, the flattest
+// response in the pass band
+const BUTTERWORTH_Q: [f64; 2] = [0.541_196_1, 1.306_563];
+
+// The high-pass cutoff is an octave below the lowest note, so the lowest note isn't attenuated
+const HIGH_PASS_RATIO: f64 = 0.5;
+
+// Keeps the frequency range of the instrument, and notches out the mains hum if there is some.
+// Filters the samples as they are recorded: the state of the filter is kept between calls, so
+// each sample only goes through it once, even though the analysis windows overlap
+#[derive(Clone, Debug)]
+pub struct BandPassFilter {
+    sections: Vec<Biquad>,
+    // The filtered analysis window
+    window: Vec<f64>,
+}
+
+impl BandPassFilter {
+    pub fn new(sampling_rate: u32, config: &Config) -> Self {
+        // The cutoff must stay below the Nyquist frequency
+        let low_pass_cutoff = config.max_frequency.min(0.45 * sampling_rate as f64);
+        let high_pass_cutoff = config.min_frequency * HIGH_PASS_RATIO;
+
+        // Pickups and cheap audio interfaces add some hum, it can win the peak picking over a
+        // quiet note
+        let hum_frequencies = config.hum_frequency.into_iter().flat_map(|hum_frequency| {
+            (1..=HUM_HARMONICS).map(move |harmonic| hum_frequency * harmonic as f64)
+        });
+        let notches = hum_frequencies
+            .take_while(|&frequency| frequency <= low_pass_cutoff)
+            .map(|frequency| Biquad::notch(frequency, frequency / NOTCH_BANDWIDTH, sampling_rate));
+
+        let sections = BUTTERWORTH_Q
+            .iter()
+            .flat_map(|&q| {
+                [
+                    Biquad::high_pass(high_pass_cutoff, q, sampling_rate),
+                    Biquad::low_pass(low_pass_cutoff, q, sampling_rate),
+                ]
+            })
+            .chain(notches)
+            .collect();
+
+        Self {
+            sections,
+            window: vec![],
+        }
+    }
+
+    // The last new_samples of the window haven't been seen yet, filter them and return the
+    // filtered window. It has the same size as the given window
+    pub fn process(&mut self, window: &[f64], new_samples: usize) -> &[f64] {
+        let size = window.len();
+        let new_samples = new_samples.min(size);
+
+        // The window size changed, keep the end of the filtered samples
+        if self.window.len() != size {
+            let kept = self.window.len().min(size);
+            let mut resized = vec![0.0; size];
+            resized[size - kept..].copy_from_slice(&self.window[self.window.len() - kept..]);
+            self.window = resized;
+        }
+
+        self.window.copy_within(new_samples.., 0);
+        for (filtered, &sample) in self.window[size - new_samples..]
+            .iter_mut()
+            .zip(&window[size - new_samples..])
+        {
+            *filtered = self
+                .sections
+                .iter_mut()
+                .fold(sample, |x, section| section.process(x));
+        }
+
+        &self.window
+    }
+}
+
+// A second order IIR filter, in the direct form II transposed
+// Coefficients from the Audio EQ Cookbook (Robert Bristow-Johnson)
+#[derive(Clone, Debug)]
+struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    z1: f64,
+    z2: f64,
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 3]) -> Self {
+        Self {
+            b0: b[0] / a[0],
+            b1: b[1] / a[0],
+            b2: b[2] / a[0],
+            a1: a[1] / a[0],
+            a2: a[2] / a[0],
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    fn low_pass(cutoff_frequency: f64, q: f64, sampling_rate: u32) -> Self {
+        let (cos, alpha) = Self::angular_terms(cutoff_frequency, q, sampling_rate);
+
+        Self::new(
+            [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    fn high_pass(cutoff_frequency: f64, q: f64, sampling_rate: u32) -> Self {
+        let (cos, alpha) = Self::angular_terms(cutoff_frequency, q, sampling_rate);
+
+        Self::new(
+            [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    fn notch(frequency: f64, q: f64, sampling_rate: u32) -> Self {
+        let (cos, alpha) = Self::angular_terms(frequency, q, sampling_rate);
+
+        Self::new(
+            [1.0, -2.0 * cos, 1.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    fn angular_terms(frequency: f64, q: f64, sampling_rate: u32) -> (f64, f64) {
+        let w0 = 2.0 * PI * frequency / sampling_rate as f64;
+        (w0.cos(), w0.sin() / (2.0 * q))
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+}
+
+// Subtract the mean, an offset would otherwise bias the autocorrelation toward long lags
+pub fn remove_dc(samples: &[f64]) -> Vec<f64> {
+    if samples.is_empty() {
+        return vec![];
+    }
+
+    let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+    samples.iter().map(|x| x - mean).collect()
+}
+
+#[cfg(test)]
+mod filter_tests {
+    use super::*;
+    use crate::pitch_detector::rms;
+
+    const SAMPLING_RATE: u32 = 44100;
+
+    fn sine_of_size(frequency: f64, amplitude: f64, phase: f64, size: usize) -> Vec<f64> {
+        (0..size)
+            .map(|i| {
+                amplitude * (2.0 * PI * frequency * i as f64 / SAMPLING_RATE as f64 + phase).sin()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn removes_dc() {
+        let result = remove_dc(&[1.0, 2.0, 3.0]);
+        assert_eq!(result, vec![-1.0, 0.0, 1.0]);
+    }
+
+    fn hum_config(hum_frequency: f64) -> Config {
+        Config {
+            hum_frequency: Some(hum_frequency),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn removes_hum_and_harmonics() {
+        let hum: Vec<f64> = sine_of_size(60.0, 1.0, 0.3, 44100)
+            .iter()
+            .zip(sine_of_size(180.0, 0.3, 1.2, 44100))
+            .map(|(a, b)| a + b + 0.1)
+            .collect();
+
+        let mut filter = BandPassFilter::new(SAMPLING_RATE, &hum_config(60.0));
+        let result = filter.process(&hum, 44100);
+        // Skip the first samples, while the notches settle
+        assert!(rms(&result[32768..]) < 0.01, "{}", rms(&result[32768..]));
+    }
+
+    #[test]
+    fn keeps_other_frequencies() {
+        // A2 is between the 2nd and 3rd harmonics of a 50 Hz hum, G3 close to the 4th
+        for frequency in [110.0, 196.0, 440.0] {
+            let note = sine_of_size(frequency, 0.5, 0.0, 44100);
+            let with_notches = BandPassFilter::new(SAMPLING_RATE, &hum_config(50.0))
+                .process(&note, 44100)[32768..]
+                .to_vec();
+            let without_notches = BandPassFilter::new(SAMPLING_RATE, &Config::default())
+                .process(&note, 44100)[32768..]
+                .to_vec();
+
+            let ratio = rms(&with_notches) / rms(&without_notches);
+            assert!(ratio > 0.9, "{} Hz: {}", frequency, ratio);
+        }
+    }
+
+    #[test]
+    fn band_pass_keeps_instrument_range() {
+        let config = Config::default();
+        // Skip the first samples, while the filter settles
+        let filtered_rms = |frequency: f64| {
+            let samples = sine_of_size(frequency, 1.0, 0.0, 16384);
+            let mut filter = BandPassFilter::new(SAMPLING_RATE, &config);
+            let filtered = filter.process(&samples, 16384);
+            rms(&filtered[8192..]) / rms(&samples[8192..])
+        };
+
+        for frequency in [82.4, 440.0, 1000.0] {
+            assert!(filtered_rms(frequency) > 0.9, "{} Hz", frequency);
+        }
+        for frequency in [10.0, 5000.0] {
+            assert!(filtered_rms(frequency) < 0.1, "{} Hz", frequency);
+        }
+    }
+
+    #[test]
+    fn band_pass_streams_overlapping_windows() {
+        let samples = sine_of_size(110.0, 0.5, 0.0, 20000);
+        let window_size = 4096;
+        let hop = 1000;
+
+        let mut streaming = BandPassFilter::new(SAMPLING_RATE, &Config::default());
+        let mut end = window_size;
+        let mut result = streaming.process(&samples[..end], window_size);
+        while end + hop <= samples.len() {
+            end += hop;
+            result = streaming.process(&samples[end - window_size..end], hop);
+        }
+
+        let mut filter = BandPassFilter::new(SAMPLING_RATE, &Config::default());
+        let expected = filter.process(&samples[..end], end);
+
+        assert_eq!(result.len(), window_size);
+        assert_eq!(result, &expected[end - window_size..]);
+    }
+
+    #[test]
+    fn band_pass_window_resize() {
+        let samples = sine_of_size(110.0, 0.5, 0.0, 8192);
+        let mut filter = BandPassFilter::new(SAMPLING_RATE, &Config::default());
+
+        let first = filter.process(&samples[..2048], 2048).to_vec();
+        // The window grows, the samples filtered so far move to the start
+        let result = filter.process(&samples[..4096], 2048);
+
+        assert_eq!(result.len(), 4096);
+        assert_eq!(result[..2048], first[..]);
+    }
+}
@@ -0,0 +1,101 @@
+use super::{rms, Config};
+
+// Level of an analysis frame, in dBFS: 0 dB is the loudest level of the input
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Level {
+    pub rms: f64,
+    pub peak: f64,
+}
+
+impl Level {
+    // Silence is -inf dB
+    pub fn of(samples: &[f64]) -> Self {
+        let peak = samples.iter().fold(0.0, |peak: f64, x| peak.max(x.abs()));
+
+        Self {
+            rms: to_decibels(rms(samples)),
+            peak: to_decibels(peak),
+        }
+    }
+}
+
+// Tells whether someone is playing, so room noise isn't analysed as a note
+// The gate opens when the level goes above the threshold, and only closes once it drops below
+// the threshold minus the hysteresis. A decaying note hovering around the threshold would
+// otherwise flicker between a note and silence
+#[derive(Clone, Debug, Default)]
+pub struct NoiseGate {
+    open: bool,
+}
+
+impl NoiseGate {
+    pub fn new() -> Self {
+        Self { open: false }
+    }
+
+    // Returns whether the gate is open
+    pub fn update(&mut self, level: &Level, config: &Config) -> bool {
+        self.open = if self.open {
+            level.rms >= config.gate_threshold - config.gate_hysteresis
+        } else {
+            level.rms >= config.gate_threshold
+        };
+
+        self.open
+    }
+}
+
+pub(super) fn to_decibels(amplitude: f64) -> f64 {
+    20.0 * amplitude.log10()
+}
+
+#[cfg(test)]
+mod noise_gate_tests {
+    use super::*;
+
+    fn level(rms: f64) -> Level {
+        Level { rms, peak: rms }
+    }
+
+    #[test]
+    fn level_of_samples() {
+        let result = Level::of(&[0.1, -0.1, 0.1, -0.1]);
+
+        assert!((result.rms + 20.0).abs() < 1e-9);
+        assert!((result.peak + 20.0).abs() < 1e-9);
+        assert_eq!(Level::of(&[0.0; 16]).rms, f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn opens_above_threshold() {
+        let config = Config::default();
+        let mut gate = NoiseGate::new();
+
+        assert!(!gate.update(&level(config.gate_threshold - 1.0), &config));
+        assert!(gate.update(&level(config.gate_threshold + 1.0), &config));
+    }
+
+    #[test]
+    fn closes_below_hysteresis() {
+        let config = Config {
+            gate_threshold: -50.0,
+            gate_hysteresis: 6.0,
+            ..Config::default()
+        };
+        let mut gate = NoiseGate::new();
+        gate.update(&level(-40.0), &config);
+
+        // The note decays, the gate stays open until it's 6 dB below the threshold
+        assert!(gate.update(&level(-52.0), &config));
+        assert!(gate.update(&level(-55.0), &config));
+        assert!(!gate.update(&level(-57.0), &config));
+        // It needs the full threshold to open again
+        assert!(!gate.update(&level(-52.0), &config));
+    }
+
+    #[test]
+    fn silence_keeps_gate_closed() {
+        let mut gate = NoiseGate::new();
+        assert!(!gate.update(&Level::of(&[0.0; 16]), &Config::default()));
+    }
+}
@@ -0,0 +1,128 @@
+use anyhow::{anyhow, Ok, Result};
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+use std::iter;
+
+use super::{parabolic_interpolation, Config, PitchDetector};
+
+// How many harmonics are multiplied together
+const HARMONICS: usize = 3;
+
+// Zero-padding makes the frequency bins narrower, which helps with the low notes
+pub(super) const ZERO_PADDING: usize = 4;
+
+// Harmonic product spectrum, a frequency domain method
+// This detector does the following:
+// 1. Calculate the magnitude spectrum of the windowed samples
+// 2. Multiply the spectrum with its downsampled copies, so the harmonics line up on the
+// fundamental frequency
+// 3. Pick the highest peak of the product and refine it on the original spectrum
+#[derive(Clone, Debug, Default)]
+pub struct HarmonicProductSpectrum {}
+
+impl HarmonicProductSpectrum {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl PitchDetector for HarmonicProductSpectrum {
+    fn detect_frequency(
+        &self,
+        samples: &[f64],
+        sampling_rate: u32,
+        config: &Config,
+    ) -> Result<(f64, f64)> {
+        let fft_size = (samples.len() * ZERO_PADDING).next_power_of_two();
+        let spectrum = magnitude_spectrum(samples, fft_size);
+        let bin_width = sampling_rate as f64 / fft_size as f64;
+
+        let min_bin = (config.min_frequency / bin_width).floor() as usize;
+        let max_bin = ((config.max_frequency / bin_width).ceil() as usize)
+            .min((spectrum.len() - 1) / HARMONICS);
+
+        let product: Vec<f64> = (0..=max_bin)
+            .map(|bin| {
+                (1..=HARMONICS)
+                    .map(|harmonic| spectrum[bin * harmonic])
+                    .product()
+            })
+            .collect();
+
+        let peak = (min_bin..=max_bin)
+            .max_by(|&b1, &b2| f64::total_cmp(&product[b1], &product[b2]))
+            .filter(|&bin| product[bin] > 0.0)
+            .ok_or(anyhow!(
+                "Can't find a peak in the harmonic product spectrum"
+            ))?;
+
+        // The product is too coarse to interpolate. Refine the peak on the strongest harmonic
+        // of the original spectrum instead, the fundamental itself is often weak
+        // The harmonics of a high note can fall past the end of the spectrum, those are skipped.
+        // The interpolation needs a bin on each side of the peak
+        let last_bin = spectrum.len().saturating_sub(2);
+        let (bin, harmonic) = (1..=HARMONICS)
+            .filter_map(|harmonic| {
+                let center = peak * harmonic;
+                (center.saturating_sub(harmonic).max(1)..=(center + harmonic).min(last_bin))
+                    .max_by(|&b1, &b2| f64::total_cmp(&spectrum[b1], &spectrum[b2]))
+                    .map(|bin| (bin, harmonic))
+            })
+            .max_by(|&(b1, _), &(b2, _)| f64::total_cmp(&spectrum[b1], &spectrum[b2]))
+            .ok_or(anyhow!("The peak is at the edge of the spectrum"))?;
+
+        // A Hann windowed peak is closer to a parabola on a logarithmic scale
+        let log_spectrum: Vec<f64> = spectrum[bin - 1..=bin + 1]
+            .iter()
+            .map(|x| x.max(f64::MIN_POSITIVE).ln())
+            .collect();
+        let interpolated_bin = bin as f64 - 1.0 + parabolic_interpolation(&log_spectrum, 1);
+        let fundamental_bin = interpolated_bin / harmonic as f64;
+
+        Ok((
+            fundamental_bin * bin_width,
+            harmonic_energy_ratio(&spectrum, fundamental_bin),
+        ))
+    }
+}
+
+// Clarity of the spectrum: the ratio of energy which lies on the harmonics of the fundamental
+fn harmonic_energy_ratio(spectrum: &[f64], fundamental_bin: f64) -> f64 {
+    // Width of the main lobe of a Hann window, in bins
+    let lobe_width = 2 * ZERO_PADDING;
+    let total_energy: f64 = spectrum.iter().map(|x| x.powi(2)).sum();
+
+    let harmonic_energy: f64 = (1..)
+        .map(|harmonic| (fundamental_bin * harmonic as f64).round() as usize)
+        .take_while(|&bin| bin + lobe_width < spectrum.len())
+        .map(|bin| {
+            spectrum[bin.saturating_sub(lobe_width)..=bin + lobe_width]
+                .iter()
+                .map(|x| x.powi(2))
+                .sum::<f64>()
+        })
+        .sum();
+
+    if total_energy == 0.0 {
+        0.0
+    } else {
+        (harmonic_energy / total_energy).clamp(0.0, 1.0)
+    }
+}
+
+pub(super) fn magnitude_spectrum(samples: &[f64], fft_size: usize) -> Vec<f64> {
+    let mut planner = FftPlanner::<f64>::new();
+
+    let mut buffer: Vec<Complex<f64>> = samples
+        .iter()
+        .zip(apodize::hanning_iter(samples.len()))
+        .map(|(&x, w)| Complex::new(x * w, 0.0))
+        .chain(iter::repeat(Complex::new(0.0, 0.0)))
+        .take(fft_size)
+        .collect();
+
+    planner.plan_fft_forward(fft_size).process(&mut buffer);
+
+    // The upper half mirrors the lower half for real signals
+    buffer.iter().take(fft_size / 2).map(|x| x.norm()).collect()
+}
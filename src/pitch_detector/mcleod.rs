@@ -0,0 +1,284 @@
+use anyhow::{anyhow, Ok, Result};
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+use std::iter;
+
+use super::{Config, PitchDetector};
+
+// The McLeod pitch method (MPM), using the auto-correlation method
+// This detector does the following:
+// 1. Calculate the normalize square difference of the samples
+// 2. Extracts peaks from the graph
+// 3. Pick to correct peak
+#[derive(Clone, Debug, Default)]
+pub struct McLeod {}
+
+impl McLeod {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl PitchDetector for McLeod {
+    fn detect_frequency(
+        &self,
+        samples: &[f64],
+        sampling_rate: u32,
+        config: &Config,
+    ) -> Result<(f64, f64)> {
+        let nsd: Vec<f64> = normalized_square_difference(samples);
+
+        // Lags longer than the period of the lowest frequency can't be the fundamental
+        let max_lag =
+            ((sampling_rate as f64 / config.min_frequency).ceil() as usize).clamp(1, nsd.len());
+        infer_fundamental_frequency(&nsd[..max_lag], sampling_rate)
+    }
+}
+
+// Normalized square difference function (NSDF) as described in the McLeod paper:
+// n'(τ) = 2 * r'(τ) / m'(τ)
+// The autocorrelation r'(τ) of every lag is computed at once with an FFT and the energy
+// term m'(τ) is computed incrementally, which brings the cost down from O(n²) to O(n log n)
+fn normalized_square_difference(samples: &[f64]) -> Vec<f64> {
+    let size = samples.len();
+    let autocorrelation = autocorrelation(samples);
+
+    // m'(0) = 2 * sum(x²), then m'(τ) = m'(τ - 1) - x(τ - 1)² - x(W - τ)²
+    let mut energy: f64 = 2.0 * samples.iter().map(|x| x.powi(2)).sum::<f64>();
+
+    (0..size)
+        .map(|lag| {
+            if lag > 0 {
+                energy -= samples[lag - 1].powi(2) + samples[size - lag].powi(2);
+            }
+
+            2.0 * autocorrelation[lag] / energy
+        })
+        .collect()
+}
+
+// r'(τ) = sum(x(i) * x(i + τ)) for every lag τ
+// Zero-padding to at least twice the size avoids the circular wrap around of the FFT
+fn autocorrelation(samples: &[f64]) -> Vec<f64> {
+    let fft_size = (2 * samples.len()).next_power_of_two();
+    let mut planner = FftPlanner::<f64>::new();
+
+    let mut buffer: Vec<Complex<f64>> = samples
+        .iter()
+        .map(|&x| Complex::new(x, 0.0))
+        .chain(iter::repeat(Complex::new(0.0, 0.0)))
+        .take(fft_size)
+        .collect();
+
+    planner.plan_fft_forward(fft_size).process(&mut buffer);
+
+    // The power spectrum is the Fourier transform of the autocorrelation
+    for value in buffer.iter_mut() {
+        *value = Complex::new(value.norm_sqr(), 0.0);
+    }
+
+    planner.plan_fft_inverse(fft_size).process(&mut buffer);
+
+    // rustfft doesn't normalize the inverse transform
+    buffer
+        .iter()
+        .take(samples.len())
+        .map(|x| x.re / fft_size as f64)
+        .collect()
+}
+
+enum YAxis {
+    Positive,
+    Negative,
+}
+
+#[derive(Debug, Clone)]
+struct KeyMaxima {
+    index: usize,
+    max: f64,
+    left_neighbor: Option<f64>,
+    right_neighbor: Option<f64>,
+}
+
+// Returns the frequency and the clarity, which is the height of the picked NSDF peak
+fn infer_fundamental_frequency(samples: &[f64], sampling_rate: u32) -> Result<(f64, f64)> {
+    let maximas = key_local_maximas(samples);
+    let best_maxima = pick_maxima(&maximas).ok_or(anyhow!("Can't the best local maxima"))?;
+    let interpolated_index = parabolic_interpolation(&best_maxima);
+    let clarity = best_maxima.max.clamp(0.0, 1.0);
+
+    Ok((sampling_rate as f64 / interpolated_index, clarity))
+}
+
+fn key_local_maximas(samples: &[f64]) -> Vec<KeyMaxima> {
+    // It always starts with a negative slope
+    let mut state = YAxis::Positive;
+    let mut prev: f64 = samples[0];
+    let mut max: Option<(usize, f64)> = None;
+    let mut maximas: Vec<KeyMaxima> = vec![];
+
+    let get_neighbor = |index: usize| -> KeyMaxima {
+        let left = if index > 0 {
+            Some(samples[index - 1])
+        } else {
+            None
+        };
+        let right = if index < samples.len() - 1 {
+            Some(samples[index + 1])
+        } else {
+            None
+        };
+
+        KeyMaxima {
+            index,
+            max: samples[index],
+            left_neighbor: left,
+            right_neighbor: right,
+        }
+    };
+
+    for (index, &sample) in samples.iter().enumerate() {
+        if prev > 0.0 && sample <= 0.0 || prev <= 0.0 && sample > 0.0 {
+            // Zero crossing
+            match state {
+                YAxis::Positive => {
+                    if let Some((index, _)) = max {
+                        maximas.push(get_neighbor(index));
+                    }
+
+                    max = None;
+                    state = YAxis::Negative;
+                }
+
+                YAxis::Negative => {
+                    state = YAxis::Positive;
+                }
+            }
+        }
+
+        if matches!(state, YAxis::Positive) {
+            match max {
+                Some((_, current_max)) if current_max < sample => max = Some((index, sample)),
+                Some(_) => (),
+                None => max = Some((index, sample)),
+            }
+        }
+
+        prev = sample;
+    }
+
+    if let Some((index, _)) = max {
+        maximas.push(get_neighbor(index));
+    }
+
+    maximas
+}
+
+const MAXIMA_THRESHOLD: f64 = 0.85;
+
+fn pick_maxima(maximas: &[KeyMaxima]) -> Option<KeyMaxima> {
+    let max = maximas
+        .iter()
+        .skip(1)
+        .max_by(|m1, m2| f64::total_cmp(&m1.max, &m2.max));
+
+    if let Some(KeyMaxima { max: max_value, .. }) = max {
+        maximas
+            .iter()
+            .skip(1)
+            .find(|m| m.max >= max_value * MAXIMA_THRESHOLD)
+            .cloned()
+    } else {
+        None
+    }
+}
+
+// Given three points, plot a parabole through 3 points and interpolate the maximum/minimum
+fn parabolic_interpolation(points: &KeyMaxima) -> f64 {
+    match points {
+        KeyMaxima {
+            index,
+            left_neighbor: None,
+            ..
+        } => *index as f64,
+
+        KeyMaxima {
+            index,
+            right_neighbor: None,
+            ..
+        } => *index as f64,
+
+        KeyMaxima {
+            index,
+            max,
+            left_neighbor: Some(left),
+            right_neighbor: Some(right),
+        } => {
+            // Parabolic formula with 3 coefficients: ax^2 + bx + c = y
+            // With three point (0, left), (1, mid) and (2, right), we have 3 equations
+            // and can find all coefficients
+            // The maximum of parabola is at x = -b / 2a
+            let a = (left + right) / 2.0 - max;
+            let b = 2.0 * max - 0.5 * right - 1.5 * left;
+            let interpolated_max = -b / (2.0 * a);
+
+            (*index as f64) - 1.0 + interpolated_max
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{read_fixture, BandPassFilter};
+    use super::*;
+    use crate::guitar::Note;
+    use crate::FIXTURE_SAMPLE_RATE;
+
+    // The straightforward O(n²) implementation, used as a reference for the FFT based one
+    fn naive_normalized_square_difference(samples: &[f64]) -> Vec<f64> {
+        (0..samples.len())
+            .map(|lag| {
+                let numerator: f64 = (0..(samples.len() - lag))
+                    .map(|i| samples[i] * samples[i + lag])
+                    .sum();
+
+                let denominator: f64 = (0..(samples.len() - lag))
+                    .map(|i| samples[i].powi(2) + samples[i + lag].powi(2))
+                    .sum();
+
+                2.0 * numerator / denominator
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nsdf_matches_naive_implementation() {
+        let samples: Vec<f64> = (0..1000).map(|_| rand::random::<f64>() - 0.5).collect();
+
+        let expected = naive_normalized_square_difference(&samples);
+        let result = normalized_square_difference(&samples);
+
+        assert_eq!(result.len(), expected.len());
+        assert!(result
+            .iter()
+            .zip(expected.iter())
+            .all(|(r, e)| (r - e).abs() < 1e-6));
+    }
+
+    #[test]
+    fn nsdf_infers_same_frequency_as_naive_implementation() {
+        for note in ["E4", "B3", "G3", "D3", "E2"] {
+            let samples = read_fixture(&Note::new(note));
+            let mut filter = BandPassFilter::new(FIXTURE_SAMPLE_RATE, &Config::default());
+            let samples = filter.process(&samples[..4096], 4096);
+
+            let expected = naive_normalized_square_difference(samples);
+            let (expected, _) =
+                infer_fundamental_frequency(&expected, FIXTURE_SAMPLE_RATE).unwrap();
+            let result = normalized_square_difference(samples);
+            let (result, _) = infer_fundamental_frequency(&result, FIXTURE_SAMPLE_RATE).unwrap();
+
+            assert!((result - expected).abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,1199 @@
+use crate::guitar::{cents_difference, get_note_frequency, nearest_note, Note, A4_FREQUENCY};
+use anyhow::{anyhow, Error, Ok, Result};
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::str::FromStr;
+
+pub use filter::BandPassFilter;
+pub use gate::{Level, NoiseGate};
+pub use hps::HarmonicProductSpectrum;
+pub use mcleod::McLeod;
+pub use onset::OnsetDetector;
+pub use tracker::PitchTracker;
+pub use yin::Yin;
+
+mod filter;
+mod gate;
+mod hps;
+mod mcleod;
+mod onset;
+mod subharmonic;
+mod tracker;
+mod yin;
+
+// Guitar notes have range of 75Hz - 1320Hz (accounted for overtones)
+const MAX_FREQUENCY: f64 = 1325.0;
+const MIN_FREQUENCY: f64 = 60.0;
+
+// The analysis window holds at least this many periods of the lowest frequency, fewer
+// periods make the low notes unreliable
+const MIN_PERIODS: f64 = 4.0;
+const MIN_WINDOW_SIZE: usize = 1 << 11;
+
+// In cents. 100 cents is 1 semitone
+const DEFAULT_TOLERANCE: f64 = 50.0;
+// Half an octave, further away a pitch is closer to the octave of the note
+const MAX_TOLERANCE: f64 = 600.0;
+
+// In dBFS. A plucked string is usually around -40 to -20 dB, the noise of a quiet room below -60
+const DEFAULT_GATE_THRESHOLD: f64 = -60.0;
+const MIN_GATE_THRESHOLD: f64 = -120.0;
+// In dB
+const DEFAULT_GATE_HYSTERESIS: f64 = 6.0;
+
+// The candidate fundamentals are the detected frequency divided by 1 up to this
+pub const MAX_SUBHARMONIC: usize = 5;
+
+// A candidate fundamental can match a string if it scores at least this ratio of the best
+// candidate. Below, the energy which would be at its harmonics isn't there, it's most likely
+// another string ringing at a harmonic of the candidate
+const MIN_CANDIDATE_SCORE: f64 = 0.8;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Config {
+    // How far, in cents, a pitch can be from a tuning note and still be matched to it
+    pub tolerance: f64,
+    // Frequency of A4, all the other notes are tuned relatively to it
+    pub reference_pitch: f64,
+    // In Hz, the range of frequencies the detectors look for, depends on the instrument
+    pub min_frequency: f64,
+    pub max_frequency: f64,
+    // In Hz, frequency of the mains hum to filter out, 50 or 60 depending on the country
+    pub hum_frequency: Option<f64>,
+    // In dBFS, frames quieter than this are silence and aren't analysed
+    pub gate_threshold: f64,
+    // In dB, how far below the threshold the level must drop to be silence again
+    pub gate_hysteresis: f64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            tolerance: DEFAULT_TOLERANCE,
+            reference_pitch: A4_FREQUENCY,
+            min_frequency: MIN_FREQUENCY,
+            max_frequency: MAX_FREQUENCY,
+            hum_frequency: None,
+            gate_threshold: DEFAULT_GATE_THRESHOLD,
+            gate_hysteresis: DEFAULT_GATE_HYSTERESIS,
+        }
+    }
+}
+
+// Everything we know about the pitch of an analysis frame
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PitchEstimate {
+    pub frequency: f64,
+    // The closest equal-tempered note, regardless of the tuning notes
+    pub note: Note,
+    // How far the frequency is from the note
+    pub cents: f64,
+    // How periodic the samples are, from 0.0 (noise) to 1.0 (a pure tone)
+    pub clarity: f64,
+    // Root mean square level of the filtered samples
+    pub rms: f64,
+    // The frequency found by the detector and its subharmonics, in this order
+    pub candidates: [Candidate; MAX_SUBHARMONIC],
+}
+
+// A possible fundamental frequency of an analysis frame
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Candidate {
+    pub frequency: f64,
+    // Weighted sum of the spectrum at the harmonics of the frequency
+    pub score: f64,
+}
+
+pub trait PitchDetector {
+    // Estimate the fundamental frequency of the samples, within the frequency range of the
+    // config. The samples are already band-pass filtered
+    // Returns the frequency and its clarity, from 0.0 to 1.0
+    fn detect_frequency(
+        &self,
+        samples: &[f64],
+        sampling_rate: u32,
+        config: &Config,
+    ) -> Result<(f64, f64)>;
+
+    // Filter out the DC offset and the frequencies which are out of the instrument range, then
+    // estimate the pitch of the samples
+    // Meant for one-off analysis. Live input goes through a BandPassFilter kept across frames
+    // instead, see estimate_filtered_pitch
+    fn estimate_pitch(
+        &self,
+        samples: &[f64],
+        sampling_rate: u32,
+        config: &Config,
+    ) -> Result<PitchEstimate> {
+        // The filter starts at rest, an offset would make it ring at the start of the window
+        let samples = filter::remove_dc(samples);
+        let mut filter = BandPassFilter::new(sampling_rate, config);
+        let filtered_samples = filter.process(&samples, samples.len());
+
+        self.estimate_filtered_pitch(filtered_samples, sampling_rate, config)
+    }
+
+    // Estimate the pitch of samples which are already band-pass filtered
+    fn estimate_filtered_pitch(
+        &self,
+        samples: &[f64],
+        sampling_rate: u32,
+        config: &Config,
+    ) -> Result<PitchEstimate> {
+        let (frequency, clarity) = self.detect_frequency(samples, sampling_rate, config)?;
+        let candidates =
+            subharmonic::subharmonic_candidates(samples, sampling_rate, frequency, config);
+
+        // The detector may have locked on a harmonic, the fundamental is the candidate which
+        // explains most of the spectrum. On equal scores, the detected frequency wins
+        let frequency = candidates
+            .iter()
+            .rev()
+            .max_by(|c1, c2| f64::total_cmp(&c1.score, &c2.score))
+            .map_or(frequency, |candidate| candidate.frequency);
+        let (note, cents) = nearest_note(frequency, config.reference_pitch);
+
+        Ok(PitchEstimate {
+            frequency,
+            note,
+            cents,
+            clarity,
+            rms: rms(samples),
+            candidates,
+        })
+    }
+
+    // Infer the closest note among the tuning notes
+    fn detect_note(
+        &self,
+        samples: &[f64],
+        sampling_rate: u32,
+        tuning_notes: &[Note],
+        config: &Config,
+    ) -> Result<(Note, f64)> {
+        let estimate = self.estimate_pitch(samples, sampling_rate, config)?;
+
+        match_tuning_note(&estimate, tuning_notes, config).ok_or(anyhow!("Fail to detect note"))
+    }
+}
+
+// Infer the tuning note played in the estimated frame
+// The frequency found by the detector might be one of the overtones, and an overtone might be
+// close to another string. Pick the candidate fundamental matching a tuning note which explains
+// the spectrum best, as long as it's not much worse than the best candidate overall
+pub fn match_tuning_note(
+    estimate: &PitchEstimate,
+    tuning_notes: &[Note],
+    config: &Config,
+) -> Option<(Note, f64)> {
+    let best_score = estimate
+        .candidates
+        .iter()
+        .map(|candidate| candidate.score)
+        .fold(0.0, f64::max);
+
+    estimate
+        .candidates
+        .iter()
+        .filter(|candidate| candidate.score >= best_score * MIN_CANDIDATE_SCORE)
+        .filter_map(|candidate| {
+            infer_tuning_note(candidate.frequency, tuning_notes, config)
+                .map(|result| (result, candidate.score))
+        })
+        // On equal scores, the higher frequency wins
+        .rev()
+        .max_by(|(_, s1), (_, s2)| f64::total_cmp(s1, s2))
+        .map(|(result, _)| result)
+}
+
+// Infer the closest note among the tuning notes, the frequency is the fundamental
+pub fn infer_tuning_note(
+    frequency: f64,
+    tuning_notes: &[Note],
+    config: &Config,
+) -> Option<(Note, f64)> {
+    infer_note(frequency, tuning_notes, config).map(|note| (note, frequency))
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Algorithm {
+    McLeod,
+    Yin,
+    HarmonicProductSpectrum,
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let algorithm_string = match self {
+            Algorithm::McLeod => "mpm",
+            Algorithm::Yin => "yin",
+            Algorithm::HarmonicProductSpectrum => "hps",
+        };
+
+        write!(f, "{}", algorithm_string)
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mpm" | "mcleod" => Ok(Algorithm::McLeod),
+            "yin" => Ok(Algorithm::Yin),
+            "hps" => Ok(Algorithm::HarmonicProductSpectrum),
+            _ => Err(anyhow!("Invalid algorithm, expected one of: mpm, yin, hps")),
+        }
+    }
+}
+
+impl Algorithm {
+    pub fn detector(&self) -> Box<dyn PitchDetector + Send> {
+        match self {
+            Algorithm::McLeod => Box::new(McLeod::new()),
+            Algorithm::Yin => Box::new(Yin::new()),
+            Algorithm::HarmonicProductSpectrum => Box::new(HarmonicProductSpectrum::new()),
+        }
+    }
+}
+
+// Infer which note is playing based on proximity of frequency
+// The proximity is measured in cents, so the tolerance is the same for low and high strings. If
+// the difference is bigger than the tolerance, we don't consider that note
+// Returns None if we can't infer any note
+fn infer_note(frequency: f64, tuning_notes: &[Note], config: &Config) -> Option<Note> {
+    tuning_notes
+        .iter()
+        .filter_map(|note| {
+            let note_frequency = get_note_frequency(note, config.reference_pitch);
+            let diff = cents_difference(frequency, note_frequency).abs();
+            if diff <= config.tolerance {
+                Some((note, diff))
+            } else {
+                None
+            }
+        })
+        .min_by(|x1, x2| f64::total_cmp(&x1.1, &x2.1))
+        .map(|(note, _)| *note)
+}
+
+// Number of samples to analyze at once, long enough for the lowest frequency of the config.
+// Always a power of two, which suits the FFT based detectors
+pub fn window_size(sampling_rate: u32, config: &Config) -> usize {
+    let size = (MIN_PERIODS * sampling_rate as f64 / config.min_frequency).ceil() as usize;
+    size.next_power_of_two().max(MIN_WINDOW_SIZE)
+}
+
+// Mains hum is 50Hz or 60Hz, depending on the country
+// In cents
+pub fn parse_tolerance(value: &str) -> Result<f64, String> {
+    let tolerance: f64 = value
+        .parse()
+        .map_err(|_| format!("{} is not a number of cents", value))?;
+
+    // Written so that NaN is rejected too
+    if !(tolerance > 0.0 && tolerance <= MAX_TOLERANCE) {
+        return Err(format!(
+            "tolerance must be above 0 and at most {} cents",
+            MAX_TOLERANCE
+        ));
+    }
+
+    Result::Ok(tolerance)
+}
+
+pub fn parse_hum_frequency(value: &str) -> Result<f64, String> {
+    let hum_frequency: f64 = value
+        .parse()
+        .map_err(|_| format!("{} is not a frequency", value))?;
+
+    if hum_frequency != 50.0 && hum_frequency != 60.0 {
+        return Err("hum frequency must be 50 Hz or 60 Hz".to_string());
+    }
+
+    Result::Ok(hum_frequency)
+}
+
+// In dBFS, 0 dB is the loudest level of the input
+pub fn parse_gate_threshold(value: &str) -> Result<f64, String> {
+    let gate_threshold: f64 = value
+        .parse()
+        .map_err(|_| format!("{} is not a level in dB", value))?;
+
+    if !(MIN_GATE_THRESHOLD..=0.0).contains(&gate_threshold) {
+        return Err(format!(
+            "gate threshold must be between {} dB and 0 dB",
+            MIN_GATE_THRESHOLD
+        ));
+    }
+
+    Result::Ok(gate_threshold)
+}
+
+pub fn parse_gate_hysteresis(value: &str) -> Result<f64, String> {
+    let gate_hysteresis: f64 = value
+        .parse()
+        .map_err(|_| format!("{} is not a level in dB", value))?;
+
+    if gate_hysteresis < 0.0 {
+        return Err("gate hysteresis can't be negative".to_string());
+    }
+
+    Result::Ok(gate_hysteresis)
+}
+
+pub fn rms(samples: &[f64]) -> f64 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+
+    (samples.iter().map(|x| x.powi(2)).sum::<f64>() / samples.len() as f64).sqrt()
+}
+
+// Fit a parabola through the value at index and its two neighbors, returns the interpolated
+// index of the parabola vertex. Works for both maximum and minimum
+fn parabolic_interpolation(values: &[f64], index: usize) -> f64 {
+    if index == 0 || index + 1 >= values.len() {
+        return index as f64;
+    }
+
+    let (left, mid, right) = (values[index - 1], values[index], values[index + 1]);
+    let denominator = left - 2.0 * mid + right;
+
+    if denominator == 0.0 {
+        index as f64
+    } else {
+        index as f64 + 0.5 * (left - right) / denominator
+    }
+}
+
+#[allow(dead_code)]
+fn plot_graph(samples: &[f64], index: usize) {
+    let filename = format!("debug/frequencies_{}.json", index);
+    let mut file = File::create(filename).unwrap();
+
+    // let payload: String = samples[..2048]
+    let payload: String = samples
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<String>>()
+        .join(",");
+
+    file.write_all(format!("[{}]", payload).as_bytes()).unwrap();
+}
+
+#[cfg(test)]
+fn read_fixture(note: &Note) -> Vec<f64> {
+    let fixture = format!("test/fixtures/{}{}_pcm", note.note, note.octave);
+
+    std::fs::read_to_string(fixture)
+        .unwrap()
+        .lines()
+        .map(|x| x.parse().unwrap())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::guitar::Tuning;
+    use crate::FIXTURE_SAMPLE_RATE;
+    use std::f64::consts::PI;
+
+    fn overlap_chunks(samples: &[f64], chunk_size: usize, move_index: usize) -> Vec<Vec<f64>> {
+        let mut index = 0;
+        let mut chunks: Vec<Vec<f64>> = vec![];
+
+        while index + chunk_size <= samples.len() {
+            chunks.push(samples[index..index + chunk_size].to_vec());
+            index += move_index;
+        }
+
+        chunks
+    }
+
+    fn detect_fixture_pitch(detector: &dyn PitchDetector, note: &Note) -> Vec<(Note, f64)> {
+        let tuning_notes = Tuning::standard().notes;
+
+        let samples = read_fixture(note);
+        let chunk_size = 8192;
+
+        overlap_chunks(&samples, chunk_size, chunk_size / 2)
+            .into_iter()
+            .take(5)
+            .filter(|chunk| chunk.len() == chunk_size)
+            .map(|chunk| {
+                detector
+                    .detect_note(
+                        &chunk,
+                        FIXTURE_SAMPLE_RATE,
+                        &tuning_notes,
+                        &Config::default(),
+                    )
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_can_detect_e4() {
+        let result = detect_fixture_pitch(&McLeod::new(), &Note::new("E4"));
+
+        let all_match = result.iter().all(|(n, _)| *n == Note::new("E4"));
+        assert!(all_match);
+
+        let target_frequency = get_note_frequency(&Note::new("E4"), A4_FREQUENCY);
+        let detected_frequencies = result.iter().map(|&(_, f)| f).collect::<Vec<f64>>();
+
+        assert!(detected_frequencies
+            .iter()
+            .all(|&f| (f - target_frequency).abs() < 1.0));
+    }
+
+    #[test]
+    fn it_can_detect_b3() {
+        let result = detect_fixture_pitch(&McLeod::new(), &Note::new("B3"));
+
+        let all_match = result.iter().all(|(n, _)| *n == Note::new("B3"));
+        assert!(all_match);
+
+        let target_frequency = get_note_frequency(&Note::new("B3"), A4_FREQUENCY);
+        let detected_frequencies = result.iter().map(|&(_, f)| f).collect::<Vec<f64>>();
+
+        assert!(detected_frequencies
+            .iter()
+            .all(|&f| (f - target_frequency).abs() < 1.0));
+    }
+
+    #[test]
+    fn it_can_detect_g3() {
+        let result = detect_fixture_pitch(&McLeod::new(), &Note::new("G3"));
+
+        let all_match = result.iter().all(|(n, _)| *n == Note::new("G3"));
+        assert!(all_match);
+
+        let target_frequency = get_note_frequency(&Note::new("G3"), A4_FREQUENCY);
+        let detected_frequencies = result.iter().map(|&(_, f)| f).collect::<Vec<f64>>();
+
+        assert!(detected_frequencies
+            .iter()
+            .all(|&f| (f - target_frequency).abs() < 1.0));
+    }
+
+    #[test]
+    fn it_can_detect_d3() {
+        let result = detect_fixture_pitch(&McLeod::new(), &Note::new("D3"));
+
+        let all_match = result.iter().all(|(n, _)| *n == Note::new("D3"));
+        assert!(all_match);
+
+        let target_frequency = get_note_frequency(&Note::new("D3"), A4_FREQUENCY);
+        let detected_frequencies = result.iter().map(|&(_, f)| f).collect::<Vec<f64>>();
+
+        assert!(detected_frequencies
+            .iter()
+            .all(|&f| (f - target_frequency).abs() < 1.0));
+    }
+
+    #[test]
+    fn it_can_detect_a2() {
+        let result = detect_fixture_pitch(&McLeod::new(), &Note::new("A2"));
+
+        let all_match = result.iter().all(|(n, _)| *n == Note::new("A2"));
+        assert!(all_match);
+
+        let target_frequency = get_note_frequency(&Note::new("A2"), A4_FREQUENCY);
+        let detected_frequencies = result.iter().map(|&(_, f)| f).collect::<Vec<f64>>();
+
+        assert!(detected_frequencies
+            .iter()
+            .all(|&f| (f - target_frequency).abs() < 1.0));
+    }
+
+    #[test]
+    fn it_can_detect_e2() {
+        let result = detect_fixture_pitch(&McLeod::new(), &Note::new("E2"));
+
+        let all_match = result.iter().all(|(n, _)| *n == Note::new("E2"));
+        assert!(all_match);
+
+        let target_frequency = get_note_frequency(&Note::new("E2"), A4_FREQUENCY);
+        let detected_frequencies = result.iter().map(|&(_, f)| f).collect::<Vec<f64>>();
+
+        assert!(detected_frequencies
+            .iter()
+            .all(|&f| (f - target_frequency).abs() < 1.0));
+    }
+
+    fn assert_fixtures_detected(detector: &dyn PitchDetector) {
+        for note in ["E4", "B3", "G3", "D3", "E2"] {
+            let note = Note::new(note);
+            let result = detect_fixture_pitch(detector, &note);
+            let target_frequency = get_note_frequency(&note, A4_FREQUENCY);
+
+            assert!(result.iter().all(|(n, _)| *n == note));
+            assert!(result
+                .iter()
+                .all(|&(_, f)| (f - target_frequency).abs() < 1.0));
+        }
+    }
+
+    #[test]
+    fn yin_can_detect_fixtures() {
+        assert_fixtures_detected(&Yin::new());
+    }
+
+    #[test]
+    fn hps_can_detect_fixtures() {
+        assert_fixtures_detected(&HarmonicProductSpectrum::new());
+    }
+
+    #[test]
+    fn hps_keeps_high_notes_in_the_spectrum() {
+        // The range of a mandolin, at a low sample rate the 3rd harmonic of its high notes is at
+        // the Nyquist frequency
+        let config = Config {
+            min_frequency: 150.0,
+            max_frequency: 2700.0,
+            ..Config::default()
+        };
+        let sample_rate = 12000;
+        let samples: Vec<f64> = (0..4096)
+            .map(|i| {
+                let phase = 2.0 * PI * 2000.0 * i as f64 / sample_rate as f64;
+                phase.sin() + 0.5 * (2.0 * phase).sin() + 0.3 * (3.0 * phase).cos()
+            })
+            .collect();
+
+        let estimate = HarmonicProductSpectrum::new()
+            .estimate_pitch(&samples, sample_rate, &config)
+            .unwrap();
+
+        assert!(
+            (estimate.frequency - 2000.0).abs() < 5.0,
+            "{}",
+            estimate.frequency
+        );
+    }
+
+    #[test]
+    fn parse_algorithm() {
+        assert_eq!("mpm".parse::<Algorithm>().unwrap(), Algorithm::McLeod);
+        assert_eq!("yin".parse::<Algorithm>().unwrap(), Algorithm::Yin);
+        assert_eq!(
+            "hps".parse::<Algorithm>().unwrap(),
+            Algorithm::HarmonicProductSpectrum
+        );
+        assert!("fft".parse::<Algorithm>().is_err());
+    }
+
+    #[test]
+    fn it_can_estimate_pitch() {
+        for note in ["E4", "B3", "G3", "D3", "E2"] {
+            let note = Note::new(note);
+            let samples = read_fixture(&note);
+            let estimate = McLeod::new()
+                .estimate_pitch(&samples[..8192], FIXTURE_SAMPLE_RATE, &Config::default())
+                .unwrap();
+
+            assert_eq!(estimate.note, note);
+            assert!((estimate.frequency - get_note_frequency(&note, A4_FREQUENCY)).abs() < 1.0);
+            assert!(estimate.cents.abs() < 10.0);
+            assert!(estimate.clarity > 0.8);
+            assert!(estimate.rms > 0.0);
+        }
+    }
+
+    #[test]
+    fn noise_has_low_clarity() {
+        let samples: Vec<f64> = (0..8192).map(|_| rand::random::<f64>() - 0.5).collect();
+
+        for algorithm in [
+            Algorithm::McLeod,
+            Algorithm::Yin,
+            Algorithm::HarmonicProductSpectrum,
+        ] {
+            if let Result::Ok(estimate) = algorithm.detector().estimate_pitch(
+                &samples,
+                FIXTURE_SAMPLE_RATE,
+                &Config::default(),
+            ) {
+                assert!(estimate.clarity < 0.8, "{} {:?}", algorithm, estimate);
+            }
+        }
+    }
+
+    // A plucked string like tone. Low strings have a weaker fundamental than the next harmonics
+    fn synthetic_tone(frequency: f64, size: usize) -> Vec<f64> {
+        let harmonics = [0.5, 1.0, 0.7, 0.5, 0.3, 0.2];
+
+        (0..size)
+            .map(|i| {
+                let t = i as f64 / FIXTURE_SAMPLE_RATE as f64;
+                harmonics
+                    .iter()
+                    .enumerate()
+                    .map(|(k, amplitude)| {
+                        let harmonic = (k + 1) as f64;
+                        amplitude * (2.0 * PI * frequency * harmonic * t + k as f64).sin()
+                    })
+                    .sum::<f64>()
+                    * 0.2
+            })
+            .collect()
+    }
+
+    #[test]
+    fn window_size_adapts_to_lowest_frequency() {
+        let guitar = Config::default();
+        let bass = Config {
+            min_frequency: 25.0,
+            ..Config::default()
+        };
+
+        assert_eq!(window_size(44100, &guitar), 4096);
+        assert_eq!(window_size(48000, &bass), 8192);
+        assert_eq!(window_size(96000, &bass), 16384);
+    }
+
+    // A string plucked after `silence` samples. It's sharp during the attack, then settles
+    fn plucked_tone(frequency: f64, silence: usize, size: usize) -> Vec<f64> {
+        let mut phase = 0.0;
+
+        (0..size)
+            .map(|i| {
+                let Some(i) = i.checked_sub(silence) else {
+                    return 0.0;
+                };
+                let t = i as f64 / FIXTURE_SAMPLE_RATE as f64;
+                let cents = 40.0 * (-t / 0.02).exp();
+                phase +=
+                    2.0 * PI * frequency * 2f64.powf(cents / 1200.0) / FIXTURE_SAMPLE_RATE as f64;
+
+                (-2.0 * t).exp() * (0.5 * phase.sin() + 0.3 * (2.0 * phase).sin())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_measures_the_sustain_of_a_pluck() {
+        let config = Config::default();
+        let samples = plucked_tone(110.0, 2000, 20000);
+        let mut filter = BandPassFilter::new(FIXTURE_SAMPLE_RATE, &config);
+        let mut onset = OnsetDetector::new(FIXTURE_SAMPLE_RATE);
+        let hop = 1024;
+
+        let mut plucks = 0;
+        let mut estimates = vec![];
+        for end in (4096..=samples.len()).step_by(hop) {
+            let new_samples = if end == 4096 { 4096 } else { hop };
+            let filtered = filter.process(&samples[end - 4096..end], new_samples);
+
+            if onset.update(filtered, new_samples, &config) {
+                plucks += 1;
+            }
+
+            if let Some(sustain) = onset.sustain(filtered, &config) {
+                let estimate = McLeod::new()
+                    .estimate_filtered_pitch(sustain, FIXTURE_SAMPLE_RATE, &config)
+                    .unwrap();
+                estimates.push(estimate);
+            }
+        }
+
+        assert_eq!(plucks, 1);
+        // The attack is skipped, even the first estimate is in tune
+        assert!(estimates.iter().all(|e| e.note == Note::new("A2")));
+        assert!(estimates[0].cents.abs() < 2.0, "{}", estimates[0].cents);
+
+        // Without skipping the attack, the pitch comes out sharp
+        let attack = &samples[2000 - 1024..2000 - 1024 + 4096];
+        let estimate = McLeod::new()
+            .estimate_pitch(attack, FIXTURE_SAMPLE_RATE, &config)
+            .unwrap();
+        assert!(estimate.cents > 5.0, "{}", estimate.cents);
+    }
+
+    #[test]
+    fn it_can_detect_low_notes() {
+        let config = Config {
+            min_frequency: 25.0,
+            max_frequency: 800.0,
+            ..Config::default()
+        };
+        let size = window_size(FIXTURE_SAMPLE_RATE, &config);
+
+        // Low B of a 5-string bass, low E of a bass and low F# of an 8-string guitar
+        for note in ["B0", "C1", "E1", "F#1", "B1"] {
+            let frequency = get_note_frequency(&Note::new(note), A4_FREQUENCY);
+            let samples = synthetic_tone(frequency, size);
+
+            for algorithm in [
+                Algorithm::McLeod,
+                Algorithm::Yin,
+                Algorithm::HarmonicProductSpectrum,
+            ] {
+                let estimate = algorithm
+                    .detector()
+                    .estimate_pitch(&samples, FIXTURE_SAMPLE_RATE, &config)
+                    .unwrap();
+
+                assert_eq!(
+                    estimate.note,
+                    Note::new(note),
+                    "{} {:?}",
+                    algorithm,
+                    estimate
+                );
+                assert!(estimate.cents.abs() < 5.0, "{} {:?}", algorithm, estimate);
+            }
+        }
+    }
+
+    // Mains hum with its first harmonics, on top of a DC offset
+    fn hum(size: usize, frequency: f64, amplitude: f64) -> Vec<f64> {
+        (0..size)
+            .map(|i| {
+                let t = i as f64 / FIXTURE_SAMPLE_RATE as f64;
+                let hum: f64 = (1..=3)
+                    .map(|k| {
+                        let harmonic = k as f64;
+                        amplitude / harmonic
+                            * (2.0 * PI * frequency * harmonic * t + harmonic).sin()
+                    })
+                    .sum();
+
+                hum + amplitude / 2.0
+            })
+            .collect()
+    }
+
+    // A bright pickup: the fundamental and the 2nd harmonic of the low strings are weak, the
+    // 3rd harmonic dominates
+    fn brighten(samples: &[f64]) -> Vec<f64> {
+        let config = Config {
+            min_frequency: 400.0,
+            ..Config::default()
+        };
+
+        BandPassFilter::new(FIXTURE_SAMPLE_RATE, &config)
+            .process(samples, samples.len())
+            .to_vec()
+    }
+
+    #[test]
+    fn it_can_detect_low_e_on_a_bright_pickup() {
+        let tuning_notes = Tuning::standard().notes;
+        let samples = brighten(&read_fixture(&Note::new("E2")));
+
+        for algorithm in [
+            Algorithm::McLeod,
+            Algorithm::Yin,
+            Algorithm::HarmonicProductSpectrum,
+        ] {
+            for chunk in overlap_chunks(&samples, 8192, 4096).iter().take(5) {
+                let estimate = algorithm
+                    .detector()
+                    .estimate_pitch(chunk, FIXTURE_SAMPLE_RATE, &Config::default())
+                    .unwrap();
+                // The 3rd harmonic is near B3
+                let result = match_tuning_note(&estimate, &tuning_notes, &Config::default());
+
+                assert_eq!(
+                    estimate.note,
+                    Note::new("E2"),
+                    "{} {:?}",
+                    algorithm,
+                    estimate
+                );
+                assert_eq!(result.map(|(note, _)| note), Some(Note::new("E2")));
+            }
+        }
+    }
+
+    #[test]
+    fn it_can_detect_strong_harmonics() {
+        // The 3rd harmonic is way above the fundamental and the 2nd harmonic
+        let harmonics = [0.02, 0.3, 1.0, 0.2, 0.1];
+
+        for note in ["E2", "A2", "D3"] {
+            let frequency = get_note_frequency(&Note::new(note), A4_FREQUENCY);
+            let samples: Vec<f64> = (0..8192)
+                .map(|i| {
+                    let t = i as f64 / FIXTURE_SAMPLE_RATE as f64;
+                    harmonics
+                        .iter()
+                        .enumerate()
+                        .map(|(k, amplitude)| {
+                            let harmonic = (k + 1) as f64;
+                            amplitude * (2.0 * PI * frequency * harmonic * t + harmonic).sin()
+                        })
+                        .sum::<f64>()
+                })
+                .collect();
+
+            for algorithm in [
+                Algorithm::McLeod,
+                Algorithm::Yin,
+                Algorithm::HarmonicProductSpectrum,
+            ] {
+                let estimate = algorithm
+                    .detector()
+                    .estimate_pitch(&samples, FIXTURE_SAMPLE_RATE, &Config::default())
+                    .unwrap();
+
+                assert_eq!(
+                    estimate.note,
+                    Note::new(note),
+                    "{} {:?}",
+                    algorithm,
+                    estimate
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn harmonics_do_not_match_lower_strings() {
+        // Open A has a low E but no B string, the B3 would only match the low E through its
+        // 3rd harmonic
+        let tuning_notes = "Open A".parse::<Tuning>().unwrap().notes;
+        let samples = read_fixture(&Note::new("B3"));
+
+        for chunk in overlap_chunks(&samples, 8192, 4096).iter().take(5) {
+            let estimate = McLeod::new()
+                .estimate_pitch(chunk, FIXTURE_SAMPLE_RATE, &Config::default())
+                .unwrap();
+
+            assert_eq!(estimate.note, Note::new("B3"));
+            assert_eq!(
+                match_tuning_note(&estimate, &tuning_notes, &Config::default()),
+                None
+            );
+        }
+    }
+
+    // Overlapping windows filtered as they are recorded, once the hum notches have settled
+    fn stream_filtered(samples: &[f64], config: &Config, window_size: usize) -> Vec<Vec<f64>> {
+        let mut filter = BandPassFilter::new(FIXTURE_SAMPLE_RATE, config);
+        let hop = window_size / 2;
+        let settled = FIXTURE_SAMPLE_RATE as usize;
+
+        (window_size..=samples.len())
+            .step_by(hop)
+            .map(|end| {
+                let new_samples = if end == window_size { window_size } else { hop };
+                let filtered = filter.process(&samples[end - window_size..end], new_samples);
+                (end, filtered.to_vec())
+            })
+            .filter(|(end, _)| *end >= settled)
+            .map(|(_, filtered)| filtered)
+            .collect()
+    }
+
+    #[test]
+    fn it_can_detect_fixtures_with_hum() {
+        let tuning_notes = Tuning::standard().notes;
+
+        for hum_frequency in [50.0, 60.0] {
+            let config = Config {
+                hum_frequency: Some(hum_frequency),
+                ..Config::default()
+            };
+
+            for note in ["E4", "B3", "G3", "D3", "E2"] {
+                let note = Note::new(note);
+                let samples = read_fixture(&note);
+                // Louder than the note itself
+                let hum = hum(samples.len(), hum_frequency, 3.0 * rms(&samples));
+                let samples: Vec<f64> = samples.iter().zip(hum).map(|(s, h)| s + h).collect();
+
+                for window in stream_filtered(&samples, &config, 8192).iter().take(5) {
+                    let estimate = McLeod::new()
+                        .estimate_filtered_pitch(window, FIXTURE_SAMPLE_RATE, &config)
+                        .unwrap();
+                    let result = match_tuning_note(&estimate, &tuning_notes, &config);
+
+                    assert_eq!(
+                        result.map(|(note, _)| note),
+                        Some(note),
+                        "{} Hz hum",
+                        hum_frequency
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn it_keeps_strings_close_to_the_hum_in_tune() {
+        // A2 sits between the 2nd and 3rd harmonics of a 50 Hz hum, D3 between the 2nd and
+        // 3rd of a 60 Hz hum
+        for (note, hum_frequency) in [("A2", 50.0), ("D3", 60.0)] {
+            let config = Config {
+                hum_frequency: Some(hum_frequency),
+                ..Config::default()
+            };
+            let frequency = get_note_frequency(&Note::new(note), A4_FREQUENCY);
+            let size = FIXTURE_SAMPLE_RATE as usize * 2;
+            let tone: Vec<f64> = (0..size)
+                .map(|i| {
+                    let phase = 2.0 * PI * frequency * i as f64 / FIXTURE_SAMPLE_RATE as f64;
+                    0.2 * phase.sin() + 0.1 * (2.0 * phase).sin() + 0.05 * (3.0 * phase).sin()
+                })
+                .collect();
+            let hum = hum(size, hum_frequency, 3.0 * rms(&tone));
+            let samples: Vec<f64> = tone.iter().zip(hum).map(|(s, h)| s + h).collect();
+
+            let windows = stream_filtered(&samples, &config, 4096);
+            assert!(windows.len() > 10);
+
+            for window in windows {
+                let estimate = McLeod::new()
+                    .estimate_filtered_pitch(&window, FIXTURE_SAMPLE_RATE, &config)
+                    .unwrap();
+
+                assert_eq!(estimate.note, Note::new(note), "{:?}", estimate);
+                assert!(estimate.cents.abs() < 3.0, "{} {}", note, estimate.cents);
+            }
+        }
+    }
+
+    #[test]
+    fn fixtures_open_the_noise_gate() {
+        let config = Config::default();
+
+        for note in ["E4", "B3", "G3", "D3", "E2"] {
+            let samples = read_fixture(&Note::new(note));
+            let mut filter = BandPassFilter::new(FIXTURE_SAMPLE_RATE, &config);
+            let mut gate = NoiseGate::new();
+
+            for chunk in overlap_chunks(&samples, 4096, 4096).iter().take(5) {
+                let filtered = filter.process(chunk, chunk.len());
+                assert!(gate.update(&Level::of(filtered), &config), "{}", note);
+            }
+        }
+    }
+
+    #[test]
+    fn room_noise_is_silence() {
+        let config = Config::default();
+        // Around -75 dB
+        let samples: Vec<f64> = (0..8192)
+            .map(|_| (rand::random::<f64>() - 0.5) * 0.001)
+            .collect();
+        let mut filter = BandPassFilter::new(FIXTURE_SAMPLE_RATE, &config);
+        let filtered = filter.process(&samples, 8192);
+
+        assert!(!NoiseGate::new().update(&Level::of(filtered), &config));
+    }
+
+    #[test]
+    fn parse_gate_tests() {
+        assert_eq!(parse_gate_threshold("-45"), Result::Ok(-45.0));
+        assert!(parse_gate_threshold("10").is_err());
+        assert!(parse_gate_threshold("loud").is_err());
+        assert_eq!(parse_gate_hysteresis("3.5"), Result::Ok(3.5));
+        assert!(parse_gate_hysteresis("-3").is_err());
+    }
+
+    #[test]
+    fn parse_tolerance_tests() {
+        assert_eq!(parse_tolerance("30"), Result::Ok(30.0));
+        assert_eq!(parse_tolerance("600"), Result::Ok(600.0));
+        assert!(parse_tolerance("0").is_err());
+        assert!(parse_tolerance("-10").is_err());
+        assert!(parse_tolerance("NaN").is_err());
+        assert!(parse_tolerance("700").is_err());
+        assert!(parse_tolerance("wide").is_err());
+    }
+
+    #[test]
+    fn parse_hum_frequency_tests() {
+        assert_eq!(parse_hum_frequency("50"), Result::Ok(50.0));
+        assert_eq!(parse_hum_frequency("60"), Result::Ok(60.0));
+        assert!(parse_hum_frequency("55").is_err());
+        assert!(parse_hum_frequency("mains").is_err());
+    }
+
+    #[test]
+    fn rms_of_sine_wave() {
+        let samples: Vec<f64> = (0..44100)
+            .map(|i| (2.0 * PI * 440.0 * i as f64 / 44100.0).sin())
+            .collect();
+
+        assert!((rms(&samples) - 1.0 / 2_f64.sqrt()).abs() < 1e-3);
+        assert_eq!(rms(&[]), 0.0);
+    }
+}
+
+#[cfg(test)]
+mod infer_tuning_note_tests {
+    use super::*;
+    use crate::guitar::Tuning;
+
+    fn tuning(name: &str) -> Vec<Note> {
+        name.parse::<Tuning>().unwrap().notes
+    }
+
+    fn config(tolerance: f64) -> Config {
+        Config {
+            tolerance,
+            ..Config::default()
+        }
+    }
+
+    fn estimate(candidates: [(f64, f64); MAX_SUBHARMONIC]) -> PitchEstimate {
+        let (note, cents) = nearest_note(candidates[0].0, A4_FREQUENCY);
+
+        PitchEstimate {
+            frequency: candidates[0].0,
+            note,
+            cents,
+            clarity: 0.9,
+            rms: 0.1,
+            candidates: candidates.map(|(frequency, score)| Candidate { frequency, score }),
+        }
+    }
+
+    #[test]
+    fn matches_best_candidate() {
+        let tuning_notes = tuning("Standard");
+        // The detector found the 3rd harmonic of the low E, near the B string
+        let result = match_tuning_note(
+            &estimate([
+                (247.2, 10.0),
+                (123.6, 4.0),
+                (82.4, 12.0),
+                (61.8, 3.0),
+                (49.4, 0.0),
+            ]),
+            &tuning_notes,
+            &Config::default(),
+        );
+
+        assert_eq!(result, Some((Note::new("E2"), 82.4)));
+    }
+
+    #[test]
+    fn matches_weaker_candidate() {
+        let tuning_notes = tuning("Standard");
+        // G4 isn't in the tuning, its subharmonic G3 is almost as good
+        let result = match_tuning_note(
+            &estimate([
+                (392.0, 10.0),
+                (196.0, 9.0),
+                (130.7, 5.0),
+                (98.0, 7.0),
+                (78.4, 4.0),
+            ]),
+            &tuning_notes,
+            &Config::default(),
+        );
+
+        assert_eq!(result, Some((Note::new("G3"), 196.0)));
+    }
+
+    #[test]
+    fn rejects_unsupported_candidate() {
+        // The low E would only be matched through a candidate the spectrum doesn't support
+        let tuning_notes = tuning("Open A");
+        let result = match_tuning_note(
+            &estimate([
+                (247.2, 10.0),
+                (123.6, 5.0),
+                (82.4, 6.0),
+                (61.8, 4.0),
+                (49.4, 0.0),
+            ]),
+            &tuning_notes,
+            &Config::default(),
+        );
+
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn exact_match() {
+        let tuning_notes = tuning("Standard");
+        let result = infer_tuning_note(110.0, &tuning_notes, &Config::default());
+
+        assert_eq!(result, Some((Note::new("A2"), 110.0)));
+    }
+
+    #[test]
+    fn tolerance_is_in_cents_for_high_strings() {
+        // 7.6Hz below E4, but only 40 cents
+        let tuning_notes = tuning("Standard");
+        let result = infer_tuning_note(322.0, &tuning_notes, &config(50.0));
+
+        assert_eq!(result, Some((Note::new("E4"), 322.0)));
+    }
+
+    #[test]
+    fn tolerance_is_in_cents_for_low_strings() {
+        // 3.4Hz below E2, but already 73 cents
+        let tuning_notes = tuning("Standard");
+
+        assert_eq!(infer_tuning_note(79.0, &tuning_notes, &config(50.0)), None);
+        assert_eq!(
+            infer_tuning_note(79.0, &tuning_notes, &config(100.0)),
+            Some((Note::new("E2"), 79.0))
+        );
+    }
+
+    #[test]
+    fn picks_nearest_of_close_strings() {
+        // Half-step apart strings, both within the tolerance
+        let tuning_notes = Tuning::new("Half-step apart", &["D4", "C#4"]).notes;
+
+        assert_eq!(
+            infer_tuning_note(287.0, &tuning_notes, &config(100.0)),
+            Some((Note::new("D4"), 287.0))
+        );
+        assert_eq!(
+            infer_tuning_note(281.0, &tuning_notes, &config(100.0)),
+            Some((Note::new("C#4"), 281.0))
+        );
+    }
+
+    #[test]
+    fn open_g_low_strings() {
+        let tuning_notes = tuning("Open G");
+
+        assert_eq!(
+            infer_tuning_note(97.0, &tuning_notes, &Config::default()),
+            Some((Note::new("G2"), 97.0))
+        );
+        assert_eq!(
+            infer_tuning_note(74.0, &tuning_notes, &Config::default()),
+            Some((Note::new("D2"), 74.0))
+        );
+    }
+
+    #[test]
+    fn drop_d_does_not_match_standard_low_e() {
+        let tuning_notes = tuning("Drop D");
+        let result = infer_tuning_note(82.4, &tuning_notes, &Config::default());
+
+        // E2 is a whole step above D2, too far to be matched
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn matches_with_reference_pitch() {
+        let tuning_notes = tuning("Standard");
+        let config = Config {
+            tolerance: 10.0,
+            reference_pitch: 432.0,
+            ..Config::default()
+        };
+
+        assert_eq!(infer_tuning_note(110.0, &tuning_notes, &config), None);
+        assert_eq!(
+            infer_tuning_note(108.0, &tuning_notes, &config),
+            Some((Note::new("A2"), 108.0))
+        );
+    }
+}
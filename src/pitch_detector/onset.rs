@@ -0,0 +1,265 @@
+use super::gate::to_decibels;
+use super::{rms, Config, MIN_PERIODS};
+
+// In seconds, the level is followed with frames this long
+const ONSET_FRAME_DURATION: f64 = 0.01;
+
+// In dB, how much louder than the previous frame a frame must be to start a pluck
+const ONSET_RISE: f64 = 9.0;
+
+// In seconds. The pitch of a plucked string is sharp right after the pluck, it settles once
+// the attack is over
+const ATTACK_DURATION: f64 = 0.08;
+
+// Finds when a string is plucked, so the pitch is measured on the stable sustain of the note
+// rather than on the attack
+#[derive(Clone, Debug)]
+pub struct OnsetDetector {
+    sampling_rate: u32,
+    frame_size: usize,
+    attack_size: usize,
+    // New samples which don't fill a frame yet
+    pending: Vec<f64>,
+    // In dBFS
+    previous_level: f64,
+    // Number of samples since the start of the last pluck, without the pending ones
+    since_onset: Option<usize>,
+}
+
+impl OnsetDetector {
+    pub fn new(sampling_rate: u32) -> Self {
+        Self {
+            sampling_rate,
+            // At least a sample per frame, whatever the sample rate
+            frame_size: ((ONSET_FRAME_DURATION * sampling_rate as f64) as usize).max(1),
+            attack_size: (ATTACK_DURATION * sampling_rate as f64) as usize,
+            pending: vec![],
+            previous_level: f64::NEG_INFINITY,
+            since_onset: None,
+        }
+    }
+
+    // The last new_samples of the window haven't been seen yet, look for a pluck in them
+    // Returns whether a new pluck started
+    pub fn update(&mut self, window: &[f64], new_samples: usize, config: &Config) -> bool {
+        // Samples were missed, the frames don't follow each other anymore
+        if new_samples > window.len() {
+            self.pending.clear();
+            self.since_onset = self
+                .since_onset
+                .map(|since_onset| since_onset + new_samples - window.len());
+        }
+
+        let new_samples = new_samples.min(window.len());
+        self.pending
+            .extend_from_slice(&window[window.len() - new_samples..]);
+
+        let mut plucked = false;
+        let frame_count = self.pending.len() / self.frame_size;
+
+        for frame in self.pending[..frame_count * self.frame_size].chunks(self.frame_size) {
+            let level = to_decibels(rms(frame));
+            // The attack itself is bumpy, it's not a new pluck
+            let in_attack = self
+                .since_onset
+                .is_some_and(|since_onset| since_onset < self.attack_size);
+
+            if !in_attack
+                && level >= config.gate_threshold
+                && level - self.previous_level >= ONSET_RISE
+            {
+                self.since_onset = Some(0);
+                plucked = true;
+            }
+
+            self.previous_level = level;
+            self.since_onset = self
+                .since_onset
+                .map(|since_onset| since_onset + frame.len());
+        }
+
+        self.pending.drain(..frame_count * self.frame_size);
+        plucked
+    }
+
+    // The end of the window which comes after the attack of the last pluck, None while the
+    // attack isn't over yet. The whole window if no pluck was found
+    pub fn sustain<'a>(&self, window: &'a [f64], config: &Config) -> Option<&'a [f64]> {
+        let Some(since_onset) = self.since_onset else {
+            return Some(window);
+        };
+
+        let sustain_size = (since_onset + self.pending.len()).checked_sub(self.attack_size)?;
+        // The sustain needs as many periods of the lowest note as the window is sized for
+        let min_sustain_size =
+            (MIN_PERIODS * self.sampling_rate as f64 / config.min_frequency).ceil() as usize;
+        if sustain_size < min_sustain_size.min(window.len()) {
+            return None;
+        }
+
+        Some(&window[window.len() - sustain_size.min(window.len())..])
+    }
+}
+
+#[cfg(test)]
+mod onset_tests {
+    use super::super::window_size;
+    use super::*;
+    use std::f64::consts::PI;
+
+    const SAMPLING_RATE: u32 = 44100;
+
+    // Silence, then a decaying note plucked after `silence` samples
+    fn pluck(silence: usize, size: usize) -> Vec<f64> {
+        pluck_note(110.0, silence, size)
+    }
+
+    fn pluck_note(frequency: f64, silence: usize, size: usize) -> Vec<f64> {
+        (0..size)
+            .map(|i| match i.checked_sub(silence) {
+                Some(i) => {
+                    let t = i as f64 / SAMPLING_RATE as f64;
+                    0.5 * (-3.0 * t).exp() * (2.0 * PI * frequency * t).sin()
+                }
+                None => 0.0,
+            })
+            .collect()
+    }
+
+    // Feed the samples hop by hop, like the recorder does
+    fn stream(detector: &mut OnsetDetector, samples: &[f64], window_size: usize, hop: usize) {
+        let mut end = window_size;
+        detector.update(&samples[..end], window_size, &Config::default());
+
+        while end + hop <= samples.len() {
+            end += hop;
+            detector.update(&samples[end - window_size..end], hop, &Config::default());
+        }
+    }
+
+    #[test]
+    fn detects_pluck() {
+        let samples = pluck(3000, 4096);
+        let mut detector = OnsetDetector::new(SAMPLING_RATE);
+
+        assert!(detector.update(&samples, 4096, &Config::default()));
+        // The pluck is in the middle of its attack
+        assert_eq!(detector.sustain(&samples, &Config::default()), None);
+    }
+
+    #[test]
+    fn sustain_skips_attack() {
+        let samples = pluck(1000, 20000);
+        let mut detector = OnsetDetector::new(SAMPLING_RATE);
+        // The hop isn't a multiple of the onset frames
+        let end = 4096 + 5 * 700;
+
+        stream(&mut detector, &samples[..end], 4096, 700);
+        let sustain = detector
+            .sustain(&samples[end - 4096..end], &Config::default())
+            .unwrap();
+
+        // The onset is found within a frame of 441 samples
+        let expected = end - 1000 - (ATTACK_DURATION * SAMPLING_RATE as f64) as usize;
+        assert!(
+            (expected..expected + 441).contains(&sustain.len()),
+            "{}",
+            sustain.len()
+        );
+    }
+
+    #[test]
+    fn sustain_fills_window() {
+        let samples = pluck(1000, 20000);
+        let mut detector = OnsetDetector::new(SAMPLING_RATE);
+
+        stream(&mut detector, &samples, 4096, 1000);
+        let window = &samples[samples.len() - 4096..];
+
+        assert_eq!(detector.sustain(window, &Config::default()), Some(window));
+    }
+
+    #[test]
+    fn sustain_holds_periods_of_low_b() {
+        // Low B of a 5 string bass, around 31 Hz
+        let config = Config {
+            min_frequency: 30.0,
+            ..Config::default()
+        };
+        let window_size = window_size(SAMPLING_RATE, &config);
+        let min_sustain_size = MIN_PERIODS * SAMPLING_RATE as f64 / 30.0;
+        let samples = pluck_note(30.87, 1000, 40000);
+        let mut detector = OnsetDetector::new(SAMPLING_RATE);
+
+        let mut sustains = vec![];
+        let mut plucks = 0;
+        for end in (window_size..samples.len()).step_by(1000) {
+            let new_samples = if end == window_size {
+                window_size
+            } else {
+                1000
+            };
+            if detector.update(&samples[end - window_size..end], new_samples, &config) {
+                plucks += 1;
+            }
+            sustains.push(detector.sustain(&samples[end - window_size..end], &config));
+        }
+
+        assert_eq!(plucks, 1);
+        // Nothing is analysed until the sustain is long enough, then every window is
+        assert!(sustains[0].is_none());
+        let first = sustains.iter().position(|s| s.is_some()).unwrap();
+        assert!(sustains[first..].iter().all(|s| s.is_some()));
+        assert!(sustains[first].unwrap().len() as f64 >= min_sustain_size);
+    }
+
+    #[test]
+    fn decay_is_not_a_pluck() {
+        let samples = pluck(0, 20000);
+        let mut detector = OnsetDetector::new(SAMPLING_RATE);
+
+        detector.update(&samples[..4096], 4096, &Config::default());
+        for end in (5096..20000).step_by(1000) {
+            assert!(!detector.update(&samples[end - 4096..end], 1000, &Config::default()));
+        }
+    }
+
+    #[test]
+    fn detects_new_pluck_while_ringing() {
+        let mut samples = pluck(0, 20000);
+        // Pluck the string again, louder
+        for (i, sample) in pluck(0, 10000).iter().enumerate() {
+            samples[10000 + i] += 4.0 * sample;
+        }
+        let mut detector = OnsetDetector::new(SAMPLING_RATE);
+        let config = Config::default();
+
+        detector.update(&samples[..4096], 4096, &config);
+        let plucks = (5096..20000)
+            .step_by(1000)
+            .filter(|&end| detector.update(&samples[end - 4096..end], 1000, &config))
+            .count();
+
+        assert_eq!(plucks, 1);
+    }
+
+    #[test]
+    fn quiet_noise_is_not_a_pluck() {
+        let samples: Vec<f64> = pluck(3000, 4096).iter().map(|x| x * 1e-4).collect();
+        let mut detector = OnsetDetector::new(SAMPLING_RATE);
+
+        assert!(!detector.update(&samples, 4096, &Config::default()));
+        assert_eq!(
+            detector.sustain(&samples, &Config::default()),
+            Some(&samples[..])
+        );
+    }
+
+    #[test]
+    fn tiny_sample_rate() {
+        // Shorter than a sample per onset frame
+        let mut detector = OnsetDetector::new(50);
+
+        assert!(!detector.update(&[0.0; 100], 100, &Config::default()));
+    }
+}
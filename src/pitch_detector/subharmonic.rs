@@ -0,0 +1,65 @@
+use std::array;
+
+use super::hps::{magnitude_spectrum, ZERO_PADDING};
+use super::{Candidate, Config, MAX_SUBHARMONIC};
+
+// Number of harmonics scored for each candidate
+const SCORED_HARMONICS: usize = 8;
+
+// Each harmonic weighs a bit less than the previous one. Otherwise a subharmonic would always
+// win, its harmonics include the harmonics of the detected frequency
+const HARMONIC_WEIGHT: f64 = 0.84;
+
+// Relative distance to look for a harmonic peak, about half a semitone. Strings are slightly
+// inharmonic, their overtones are a bit sharp
+const HARMONIC_RANGE: f64 = 0.03;
+
+// The detectors lock on a harmonic when it is stronger than the fundamental, e.g. the 3rd
+// harmonic of a low E on a bright pickup, which lands near the B string
+// The candidates are the detected frequency and its subharmonics, each one scored with the
+// weighted sum of the spectrum at its harmonics (subharmonic summation). A subharmonic only
+// scores higher than the detected frequency if there is energy in between its harmonics
+pub fn subharmonic_candidates(
+    samples: &[f64],
+    sampling_rate: u32,
+    frequency: f64,
+    config: &Config,
+) -> [Candidate; MAX_SUBHARMONIC] {
+    let fft_size = (samples.len() * ZERO_PADDING).next_power_of_two();
+    let spectrum = magnitude_spectrum(samples, fft_size);
+    let bin_width = sampling_rate as f64 / fft_size as f64;
+
+    let score = |fundamental: f64| -> f64 {
+        (1..=SCORED_HARMONICS)
+            .map(|harmonic| {
+                HARMONIC_WEIGHT.powi(harmonic as i32 - 1)
+                    * peak_magnitude(&spectrum, fundamental * harmonic as f64, bin_width)
+            })
+            .sum()
+    };
+
+    array::from_fn(|index| {
+        let frequency = frequency / (index + 1) as f64;
+
+        Candidate {
+            frequency,
+            // Out of the instrument range
+            score: if frequency < config.min_frequency {
+                0.0
+            } else {
+                score(frequency)
+            },
+        }
+    })
+}
+
+// Highest magnitude of the spectrum around the frequency
+fn peak_magnitude(spectrum: &[f64], frequency: f64, bin_width: f64) -> f64 {
+    let low_bin = (frequency * (1.0 - HARMONIC_RANGE) / bin_width).floor() as usize;
+    let high_bin = ((frequency * (1.0 + HARMONIC_RANGE) / bin_width).ceil() as usize)
+        .min(spectrum.len().saturating_sub(1));
+
+    spectrum
+        .get(low_bin..=high_bin)
+        .map_or(0.0, |bins| bins.iter().copied().fold(0.0, f64::max))
+}
@@ -0,0 +1,312 @@
+use std::collections::VecDeque;
+
+use crate::guitar::{cents_difference, get_note_frequency, Note};
+
+// Number of detections the median is taken over
+const MEDIAN_SIZE: usize = 3;
+
+// Weight of the new median in the exponential smoothing, from 0.0 (frozen) to 1.0 (no smoothing)
+const SMOOTHING: f64 = 0.5;
+
+// In cents, a detection this far from the median is an outlier
+const OUTLIER_RANGE: f64 = 35.0;
+
+// After this many outliers in a row, the pitch really moved, e.g. the peg was turned quickly
+const MAX_OUTLIERS: usize = 3;
+
+// Number of detections in a row of another note before switching to it
+const SWITCH_DETECTIONS: usize = 2;
+
+// Sits between the detector and the tuning bar, so the needle doesn't jump around
+// Detections are smoothed in cents from the tracked note, with a median which drops the odd
+// detection and an exponential smoothing on top of it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PitchTracker {
+    note: Option<Note>,
+    // In cents from the note, the last accepted detections
+    history: VecDeque<f64>,
+    smoothed: f64,
+    outliers: usize,
+    // Another note detected in a row, with the number of detections
+    next_note: Option<(Note, usize)>,
+}
+
+impl PitchTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Forget the tracked note, the next detection is taken as it is
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    // Add a detection, returns the tracked note and its smoothed frequency
+    pub fn update(&mut self, note: Note, frequency: f64, reference_pitch: f64) -> (Note, f64) {
+        let cents = cents_difference(frequency, get_note_frequency(&note, reference_pitch));
+
+        match self.note {
+            Some(tracked) if tracked == note => {
+                self.next_note = None;
+                self.add_detection(cents);
+            }
+
+            // Wait for the other note to be confirmed, a harmonic or noise often comes out as
+            // another note for a single frame
+            Some(tracked) => {
+                let count = match self.next_note {
+                    Some((next_note, count)) if next_note == note => count + 1,
+                    _ => 1,
+                };
+
+                if count < SWITCH_DETECTIONS {
+                    self.next_note = Some((note, count));
+                    return (tracked, self.frequency(reference_pitch).unwrap());
+                }
+
+                self.track(note, cents);
+            }
+
+            None => self.track(note, cents),
+        }
+
+        // Moved from the detection rather than computed from the note, a detection which is
+        // taken as it is comes out unchanged
+        (
+            note,
+            frequency * 2_f64.powf((self.smoothed - cents) / 1200.0),
+        )
+    }
+
+    // A detection which matches no note counts as an outlier, a single frame in the middle of a
+    // note doesn't lose it. Returns whether the note is still tracked
+    pub fn miss(&mut self) -> bool {
+        if self.note.is_none() {
+            return false;
+        }
+
+        self.outliers += 1;
+        if self.outliers >= MAX_OUTLIERS {
+            self.reset();
+            return false;
+        }
+
+        true
+    }
+
+    fn track(&mut self, note: Note, cents: f64) {
+        self.reset();
+        self.note = Some(note);
+        self.restart(cents);
+    }
+
+    fn restart(&mut self, cents: f64) {
+        self.history = VecDeque::from([cents]);
+        self.smoothed = cents;
+        self.outliers = 0;
+    }
+
+    fn add_detection(&mut self, cents: f64) {
+        if (cents - self.median()).abs() > OUTLIER_RANGE {
+            self.outliers += 1;
+
+            if self.outliers >= MAX_OUTLIERS {
+                self.restart(cents);
+            }
+
+            return;
+        }
+
+        self.outliers = 0;
+        self.history.push_back(cents);
+        if self.history.len() > MEDIAN_SIZE {
+            self.history.pop_front();
+        }
+
+        self.smoothed += SMOOTHING * (self.median() - self.smoothed);
+    }
+
+    fn median(&self) -> f64 {
+        let mut sorted: Vec<f64> = self.history.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+
+        match sorted.len() {
+            0 => 0.0,
+            len if len % 2 == 0 => (sorted[len / 2 - 1] + sorted[len / 2]) / 2.0,
+            len => sorted[len / 2],
+        }
+    }
+
+    // The smoothed frequency of the tracked note
+    pub fn frequency(&self, reference_pitch: f64) -> Option<f64> {
+        let note = self.note?;
+        Some(get_note_frequency(&note, reference_pitch) * 2_f64.powf(self.smoothed / 1200.0))
+    }
+}
+
+#[cfg(test)]
+mod pitch_tracker_tests {
+    use super::*;
+    use crate::guitar::A4_FREQUENCY;
+
+    // Frequency of the note, off by the cents
+    fn pitch(note: &str, cents: f64) -> (Note, f64) {
+        let note = Note::new(note);
+        let frequency = get_note_frequency(&note, A4_FREQUENCY) * 2_f64.powf(cents / 1200.0);
+        (note, frequency)
+    }
+
+    // Feed the detections, returns the tracked notes and their cents
+    fn track(tracker: &mut PitchTracker, detections: &[(&str, f64)]) -> Vec<(Note, f64)> {
+        detections
+            .iter()
+            .map(|&(note, cents)| {
+                let (note, frequency) = pitch(note, cents);
+                let (note, frequency) = tracker.update(note, frequency, A4_FREQUENCY);
+                (
+                    note,
+                    cents_difference(frequency, get_note_frequency(&note, A4_FREQUENCY)),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn first_detection_is_taken_as_it_is() {
+        let mut tracker = PitchTracker::new();
+        let result = track(&mut tracker, &[("E2", 12.0)]);
+
+        assert_eq!(result[0].0, Note::new("E2"));
+        assert!((result[0].1 - 12.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn smooths_jitter() {
+        let mut tracker = PitchTracker::new();
+        let detections: Vec<(&str, f64)> = (0..20)
+            .map(|i| ("A2", if i % 2 == 0 { 6.0 } else { -6.0 }))
+            .collect();
+
+        let result = track(&mut tracker, &detections);
+
+        for (_, cents) in &result[5..] {
+            assert!(cents.abs() < 3.0, "{}", cents);
+        }
+    }
+
+    #[test]
+    fn follows_slow_changes() {
+        let mut tracker = PitchTracker::new();
+        // The peg is turned, 2 cents at a time
+        let detections: Vec<(&str, f64)> =
+            (0..20).map(|i| ("D3", -30.0 + 2.0 * i as f64)).collect();
+
+        let result = track(&mut tracker, &detections);
+        let (_, cents) = result.last().unwrap();
+
+        assert!((cents - 8.0).abs() < 5.0, "{}", cents);
+    }
+
+    #[test]
+    fn rejects_outliers() {
+        let mut tracker = PitchTracker::new();
+        let result = track(
+            &mut tracker,
+            &[
+                ("G3", 2.0),
+                ("G3", 2.0),
+                ("G3", 45.0),
+                ("G3", 2.0),
+                ("G3", -40.0),
+            ],
+        );
+
+        for (_, cents) in result {
+            assert!((cents - 2.0).abs() < 1e-9, "{}", cents);
+        }
+    }
+
+    #[test]
+    fn follows_a_real_jump() {
+        let mut tracker = PitchTracker::new();
+        let result = track(
+            &mut tracker,
+            &[
+                ("B3", 0.0),
+                ("B3", 0.0),
+                ("B3", 45.0),
+                ("B3", 45.0),
+                ("B3", 45.0),
+            ],
+        );
+
+        assert!((result[3].1).abs() < 1e-9);
+        assert!((result[4].1 - 45.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn waits_before_switching_note() {
+        let mut tracker = PitchTracker::new();
+        let result = track(
+            &mut tracker,
+            &[("E2", 0.0), ("E2", 0.0), ("E3", 3.0), ("E3", 3.0)],
+        );
+
+        assert_eq!(result[2].0, Note::new("E2"));
+        assert!((result[2].1).abs() < 1e-9);
+        // The new note starts without the smoothing of the previous one
+        assert_eq!(result[3].0, Note::new("E3"));
+        assert!((result[3].1 - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn ignores_interleaved_notes() {
+        let mut tracker = PitchTracker::new();
+        let detections = [
+            ("A2", 0.0),
+            ("E4", 0.0),
+            ("A2", 0.0),
+            ("E4", 0.0),
+            ("A2", 0.0),
+        ];
+
+        let result = track(&mut tracker, &detections);
+
+        assert!(result.iter().all(|(note, _)| *note == Note::new("A2")));
+    }
+
+    #[test]
+    fn tolerates_a_miss() {
+        let mut tracker = PitchTracker::new();
+        track(&mut tracker, &[("A2", 4.0), ("A2", 4.0)]);
+
+        assert!(tracker.miss());
+        let result = track(&mut tracker, &[("A2", 4.0)]);
+
+        assert_eq!(result[0].0, Note::new("A2"));
+        assert!((result[0].1 - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn forgets_note_after_misses() {
+        let mut tracker = PitchTracker::new();
+        track(&mut tracker, &[("A2", 0.0), ("A2", 0.0)]);
+
+        assert!(tracker.miss());
+        assert!(tracker.miss());
+        assert!(!tracker.miss());
+        assert_eq!(tracker.frequency(A4_FREQUENCY), None);
+    }
+
+    #[test]
+    fn reset_forgets_note() {
+        let mut tracker = PitchTracker::new();
+        track(&mut tracker, &[("A2", 0.0), ("A2", 0.0)]);
+
+        tracker.reset();
+        let result = track(&mut tracker, &[("D3", -20.0)]);
+
+        assert_eq!(result[0].0, Note::new("D3"));
+        assert!((result[0].1 + 20.0).abs() < 1e-9);
+    }
+}
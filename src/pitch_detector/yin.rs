@@ -0,0 +1,174 @@
+use anyhow::{anyhow, Ok, Result};
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+use std::iter;
+
+use super::{parabolic_interpolation, Config, PitchDetector};
+
+const YIN_THRESHOLD: f64 = 0.2;
+
+// The YIN algorithm, described in the paper "YIN, a fundamental frequency estimator for
+// speech and music" (Alain de Cheveigné, Hideki Kawahara)
+// This detector does the following:
+// 1. Calculate the difference function of the samples
+// 2. Normalize it with its cumulative mean
+// 3. Pick the first dip below the absolute threshold
+#[derive(Clone, Debug, Default)]
+pub struct Yin {}
+
+impl Yin {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl PitchDetector for Yin {
+    fn detect_frequency(
+        &self,
+        samples: &[f64],
+        sampling_rate: u32,
+        config: &Config,
+    ) -> Result<(f64, f64)> {
+        let max_lag =
+            ((sampling_rate as f64 / config.min_frequency).ceil() as usize).min(samples.len() / 2);
+        let min_lag = (sampling_rate as f64 / config.max_frequency).floor() as usize;
+
+        let difference = difference_function(samples, max_lag);
+        let normalized = cumulative_mean_normalized_difference(&difference);
+
+        let lag = absolute_threshold(&normalized, min_lag, YIN_THRESHOLD)
+            .ok_or(anyhow!("Can't find a dip below the threshold"))?;
+        let interpolated_lag = parabolic_interpolation(&normalized, lag);
+
+        // The normalized difference is the aperiodicity of the signal at that lag
+        let clarity = (1.0 - normalized[lag]).clamp(0.0, 1.0);
+
+        Ok((sampling_rate as f64 / interpolated_lag, clarity))
+    }
+}
+
+// d(τ) = sum((x(i) - x(i + τ))²), with the sum running over a fixed size window W
+// Expanded to sum(x(i)²) + sum(x(i + τ)²) - 2 * sum(x(i) * x(i + τ)), the cross term of every
+// lag is computed at once with an FFT and the energy of the shifted window incrementally, which
+// brings the cost down from O(W * max_lag) to O(n log n)
+fn difference_function(samples: &[f64], max_lag: usize) -> Vec<f64> {
+    let window = samples.len() - max_lag;
+    let correlation = cross_correlation(&samples[..window], samples, max_lag);
+
+    let energy: f64 = samples[..window].iter().map(|x| x.powi(2)).sum();
+    // sum(x(i + τ)²) = sum(x(i + τ - 1)²) - x(τ - 1)² + x(τ + W - 1)²
+    let mut shifted_energy = energy;
+
+    (0..=max_lag)
+        .map(|lag| {
+            if lag > 0 {
+                shifted_energy += samples[lag + window - 1].powi(2) - samples[lag - 1].powi(2);
+            }
+
+            // Rounding errors can take a perfect match slightly below zero
+            (energy + shifted_energy - 2.0 * correlation[lag]).max(0.0)
+        })
+        .collect()
+}
+
+// c(τ) = sum(w(i) * x(i + τ)) for every lag τ up to max_lag
+// Zero-padding past the size of both avoids the circular wrap around of the FFT
+fn cross_correlation(window: &[f64], samples: &[f64], max_lag: usize) -> Vec<f64> {
+    let fft_size = (window.len() + samples.len()).next_power_of_two();
+    let mut planner = FftPlanner::<f64>::new();
+    let fft = planner.plan_fft_forward(fft_size);
+
+    let padded = |values: &[f64]| -> Vec<Complex<f64>> {
+        values
+            .iter()
+            .map(|&x| Complex::new(x, 0.0))
+            .chain(iter::repeat(Complex::new(0.0, 0.0)))
+            .take(fft_size)
+            .collect()
+    };
+    let mut window_spectrum = padded(window);
+    let mut buffer = padded(samples);
+    fft.process(&mut window_spectrum);
+    fft.process(&mut buffer);
+
+    // The cross-correlation is the product with the conjugate spectrum of the window
+    for (value, window_value) in buffer.iter_mut().zip(&window_spectrum) {
+        *value *= window_value.conj();
+    }
+
+    planner.plan_fft_inverse(fft_size).process(&mut buffer);
+
+    // rustfft doesn't normalize the inverse transform
+    buffer
+        .iter()
+        .take(max_lag + 1)
+        .map(|x| x.re / fft_size as f64)
+        .collect()
+}
+
+// d'(τ) = d(τ) / ((1 / τ) * sum(d(j)) for j in 1..=τ), with d'(0) = 1
+fn cumulative_mean_normalized_difference(difference: &[f64]) -> Vec<f64> {
+    let mut running_sum = 0.0;
+
+    difference
+        .iter()
+        .enumerate()
+        .map(|(lag, &value)| {
+            if lag == 0 {
+                return 1.0;
+            }
+
+            running_sum += value;
+
+            if running_sum == 0.0 {
+                1.0
+            } else {
+                value * lag as f64 / running_sum
+            }
+        })
+        .collect()
+}
+
+// Find the first lag whose value goes below the threshold, then follow the dip down to its
+// local minimum
+fn absolute_threshold(normalized: &[f64], min_lag: usize, threshold: f64) -> Option<usize> {
+    let mut lag = (min_lag.max(1)..normalized.len()).find(|&lag| normalized[lag] < threshold)?;
+
+    while lag + 1 < normalized.len() && normalized[lag + 1] < normalized[lag] {
+        lag += 1;
+    }
+
+    Some(lag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The straightforward O(W * max_lag) implementation, used as a reference for the FFT based one
+    fn naive_difference_function(samples: &[f64], max_lag: usize) -> Vec<f64> {
+        let window = samples.len() - max_lag;
+
+        (0..=max_lag)
+            .map(|lag| {
+                (0..window)
+                    .map(|i| (samples[i] - samples[i + lag]).powi(2))
+                    .sum()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn difference_matches_naive_implementation() {
+        let samples: Vec<f64> = (0..1000).map(|_| rand::random::<f64>() - 0.5).collect();
+
+        let expected = naive_difference_function(&samples, 400);
+        let result = difference_function(&samples, 400);
+
+        assert_eq!(result.len(), expected.len());
+        assert!(result
+            .iter()
+            .zip(expected.iter())
+            .all(|(r, e)| (r - e).abs() < 1e-6));
+    }
+}
@@ -1,101 +1,112 @@
-use anyhow::{anyhow, Ok, Result};
-use cpal::traits::StreamTrait;
-use cpal::traits::{DeviceTrait, HostTrait};
-use cpal::{Device, SampleFormat, SampleRate, Stream, SupportedStreamConfig};
-use std::sync::{Arc, Mutex};
+use anyhow::Result;
+
+use crate::audio_source::AudioSource;
+use crate::ring_buffer::{ring_buffer, Consumer};
+
+// Number of samples the ring buffer keeps, a few seconds of audio. Much more than an analysis
+// window, so the source has room to write while the window is read
+const RING_BUFFER_CAPACITY: usize = 1 << 18;
 
 pub struct Recorder {
-    samples: Arc<Mutex<Vec<f64>>>,
-    // Maximum size of the samples vec
+    // Number of samples handed to with_samples
     buffer_size: usize,
-    stream: Option<Stream>,
+    // Latest samples, reused from one call to the next
+    window: Vec<f64>,
+    consumer: Option<Consumer>,
+    // Number of samples recorded when with_samples was last called
+    last_recorded: usize,
+    // Kept alive while recording
+    source: Option<Box<dyn AudioSource>>,
 }
 
 impl Recorder {
     pub fn new(buffer_size: usize) -> Self {
         Self {
-            samples: Arc::new(Mutex::new(Vec::with_capacity(buffer_size))),
-            stream: None,
             buffer_size,
+            window: vec![0.0; buffer_size],
+            consumer: None,
+            last_recorded: 0,
+            source: None,
         }
     }
 
-    // Start recording audio and pulse code modulating. Each sample is a number in the range of
-    // -1.0..1.0
-    // This function will fail if the recording device doesn't support the provided sample rate
-    pub fn record(&mut self) -> Result<SampleRate> {
-        #[cfg(any(
-            not(any(
-                target_os = "linux",
-                target_os = "dragonfly",
-                target_os = "freebsd",
-                target_os = "netbsd"
-            )),
-            not(feature = "jack")
-        ))]
-        let host = cpal::default_host();
-
-        // Set up the input device and stream with the default input config.
-        let device = host
-            .default_input_device()
-            .ok_or(anyhow!("Can't find default input device"))?;
-
-        let config = get_device_input_config(&device);
-
-        let samples_clone = self.samples.clone();
-        let buffer_size = self.buffer_size;
-
-        let err_fn = move |err| {
-            eprintln!("An error occurred on stream: {}", err);
-        };
-
-        let stream = device.build_input_stream(
-            &config.clone().into(),
-            move |data: &[f32], _: &_| {
-                let mut buffer = samples_clone.lock().unwrap();
-
-                for sample in data.iter() {
-                    buffer.push(*sample as f64);
-                }
+    // Can be changed once the sample rate is known
+    pub fn set_buffer_size(&mut self, buffer_size: usize) {
+        self.buffer_size = buffer_size;
+        self.window.resize(buffer_size, 0.0);
+    }
 
-                if buffer.len() > buffer_size {
-                    resize_buffer(&mut buffer, buffer_size);
-                }
-            },
-            err_fn,
-            None,
-        )?;
+    // Start recording from the source. Each sample is a number in the range of -1.0..1.0
+    // Returns the sample rate of the source
+    pub fn record(&mut self, mut source: Box<dyn AudioSource>) -> Result<u32> {
+        let (producer, consumer) = ring_buffer(RING_BUFFER_CAPACITY.max(2 * self.buffer_size));
+        let sample_rate = source.start(producer)?;
 
-        stream.play()?;
-        self.stream = Some(stream);
+        self.source = Some(source);
+        self.consumer = Some(consumer);
+        self.last_recorded = 0;
 
-        Ok(config.sample_rate())
+        Ok(sample_rate)
     }
 
-    // Invoke callback on collected samples. Only use the last `limit` samples
-    pub fn with_samples<'a>(&'a self, mut callback: impl FnMut(Vec<f64>) -> ()) {
-        let samples = self.samples.lock().unwrap();
-        let mut clone = samples.clone();
-        drop(samples);
-        clone.resize(self.buffer_size, 0.0);
-        callback(clone);
+    // The source has no more samples, and they were all handed to with_samples
+    pub fn is_finished(&self) -> bool {
+        match (self.source.as_ref(), self.consumer.as_ref()) {
+            (Some(source), Some(consumer)) => {
+                source.is_finished() && consumer.written() == self.last_recorded
+            }
+            _ => false,
+        }
     }
-}
 
-fn get_device_input_config(device: &Device) -> SupportedStreamConfig {
-    let configs = device.supported_input_configs().unwrap();
+    // Name of the input device being recorded, if any
+    pub fn device(&self) -> Option<String> {
+        self.source.as_ref().and_then(|source| source.device())
+    }
 
-    let config = configs
-        .into_iter()
-        .filter(|config| config.channels() == 1 && config.sample_format() == SampleFormat::F32)
-        .max_by_key(|config| config.max_sample_rate())
-        .unwrap();
+    // Invoke callback on exactly the latest `buffer_size` samples, padded with zeros at the start
+    // until enough samples are recorded
+    // The callback also gets how many samples at the end are new since the last call, so they
+    // can be processed only once
+    pub fn with_samples(&mut self, mut callback: impl FnMut(&[f64], usize)) {
+        let recorded = match self.consumer.as_ref() {
+            Some(consumer) => consumer.read_latest(&mut self.window),
+            None => 0,
+        };
 
-    config.with_max_sample_rate()
+        let new_samples = recorded - self.last_recorded;
+        self.last_recorded = recorded;
+        callback(&self.window, new_samples);
+    }
 }
 
-// Take N elements from tail. Avoid allocation by copying
-fn resize_buffer(buffer: &mut Vec<f64>, amount: usize) {
-    let buffer_len = buffer.len();
-    buffer.copy_within((buffer_len - amount)..buffer_len, 0);
+#[cfg(test)]
+mod recorder_tests {
+    use super::*;
+    use crate::audio_source::FileSource;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn records_until_source_is_finished() {
+        let samples: Vec<f64> = (0..2205).map(|x| x as f64 / 2205.0).collect();
+        let mut recorder = Recorder::new(1024);
+        recorder
+            .record(Box::new(FileSource::new(&samples, 44100)))
+            .unwrap();
+
+        let mut total = 0;
+        let mut last_window = vec![];
+        while !recorder.is_finished() {
+            thread::sleep(Duration::from_millis(10));
+            recorder.with_samples(|window, new_samples| {
+                total += new_samples;
+                last_window = window.to_vec();
+            });
+        }
+
+        assert_eq!(total, samples.len());
+        assert_eq!(last_window.len(), 1024);
+        assert!((last_window[1023] - samples[2204]).abs() < 1e-6);
+    }
 }
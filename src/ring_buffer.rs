@@ -0,0 +1,228 @@
+use std::sync::atomic::{fence, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// A lock-free ring buffer with a single producer, the audio callback, and a single consumer, the
+// analysis thread. The producer never waits nor allocates, it overwrites the oldest samples.
+// The consumer only ever wants the latest samples, and reads them again if the producer
+// overwrote them in the meantime
+//
+// Samples are stored as the bits of f64 in atomics, so the consumer can read them while the
+// producer writes without any unsafe code. They keep the full precision of 32 bit devices
+#[derive(Debug)]
+struct Shared {
+    samples: Box<[AtomicU64]>,
+    // Number of samples written since the start
+    written: AtomicUsize,
+    // Number of samples the producer is about to have written, ahead of `written` while it
+    // writes. Tells the consumer which samples may have been overwritten during a read
+    claimed: AtomicUsize,
+}
+
+#[derive(Debug)]
+pub struct Producer {
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug)]
+pub struct Consumer {
+    shared: Arc<Shared>,
+}
+
+// The capacity is rounded up to a power of two
+pub fn ring_buffer(capacity: usize) -> (Producer, Consumer) {
+    let capacity = capacity.max(1).next_power_of_two();
+    let shared = Arc::new(Shared {
+        samples: (0..capacity).map(|_| AtomicU64::new(0)).collect(),
+        written: AtomicUsize::new(0),
+        claimed: AtomicUsize::new(0),
+    });
+
+    (
+        Producer {
+            shared: shared.clone(),
+        },
+        Consumer { shared },
+    )
+}
+
+impl Shared {
+    fn slot(&self, index: usize) -> &AtomicU64 {
+        &self.samples[index & (self.samples.len() - 1)]
+    }
+}
+
+impl Producer {
+    // Safe to call from the audio callback
+    pub fn push(&mut self, data: &[f64]) {
+        let shared = &self.shared;
+        let written = shared.written.load(Ordering::Relaxed);
+
+        shared
+            .claimed
+            .store(written + data.len(), Ordering::Relaxed);
+        fence(Ordering::Release);
+
+        for (index, sample) in (written..).zip(data) {
+            shared
+                .slot(index)
+                .store(sample.to_bits(), Ordering::Relaxed);
+        }
+
+        shared
+            .written
+            .store(written + data.len(), Ordering::Release);
+    }
+
+    // Whether the consumer is gone, nobody will read the samples anymore
+    pub fn is_abandoned(&self) -> bool {
+        Arc::strong_count(&self.shared) == 1
+    }
+}
+
+impl Consumer {
+    pub fn capacity(&self) -> usize {
+        self.shared.samples.len()
+    }
+
+    // Number of samples written since the start
+    pub fn written(&self) -> usize {
+        self.shared.written.load(Ordering::Acquire)
+    }
+
+    // Fill `samples` with the latest samples, the newest one last. Until enough samples are
+    // recorded, the start is padded with zeros. At most the capacity of samples can be read,
+    // the rest is padded as well
+    // Returns the number of samples written since the start, up to the newest one read
+    pub fn read_latest(&self, samples: &mut [f64]) -> usize {
+        let shared = &self.shared;
+
+        loop {
+            let written = shared.written.load(Ordering::Acquire);
+            let count = samples.len().min(written).min(self.capacity());
+            let start = written - count;
+            let padding = samples.len() - count;
+
+            samples[..padding].fill(0.0);
+            for (sample, index) in samples[padding..].iter_mut().zip(start..written) {
+                *sample = f64::from_bits(shared.slot(index).load(Ordering::Relaxed));
+            }
+
+            // The producer may have lapped the oldest samples while they were read
+            fence(Ordering::Acquire);
+            let claimed = shared.claimed.load(Ordering::Relaxed);
+            if claimed - start <= self.capacity() {
+                return written;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod ring_buffer_tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn capacity_is_power_of_two() {
+        let (_, consumer) = ring_buffer(1000);
+        assert_eq!(consumer.capacity(), 1024);
+    }
+
+    #[test]
+    fn pads_until_full() {
+        let (mut producer, consumer) = ring_buffer(8);
+        producer.push(&[1.0, 2.0, 3.0]);
+
+        let mut samples = [9.0; 5];
+        let written = consumer.read_latest(&mut samples);
+
+        assert_eq!(written, 3);
+        assert_eq!(samples, [0.0, 0.0, 1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn reads_latest_samples_after_wrapping() {
+        let (mut producer, consumer) = ring_buffer(8);
+        let data: Vec<f64> = (0..21).map(|x| x as f64).collect();
+        for chunk in data.chunks(5) {
+            producer.push(chunk);
+        }
+
+        let mut samples = [0.0; 6];
+        let written = consumer.read_latest(&mut samples);
+
+        assert_eq!(written, 21);
+        assert_eq!(samples, [15.0, 16.0, 17.0, 18.0, 19.0, 20.0]);
+    }
+
+    #[test]
+    fn reads_at_most_capacity() {
+        let (mut producer, consumer) = ring_buffer(4);
+        producer.push(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+        let mut samples = [9.0; 6];
+        consumer.read_latest(&mut samples);
+
+        assert_eq!(samples, [0.0, 0.0, 3.0, 4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn keeps_precision_of_32_bit_samples() {
+        let (mut producer, consumer) = ring_buffer(4);
+        // The smallest step of a 32 bit integer sample, lost in an f32 next to a loud sample
+        let sample = 0.5 + 1.0 / 2147483648.0;
+        producer.push(&[sample]);
+
+        let mut samples = [0.0; 1];
+        consumer.read_latest(&mut samples);
+
+        assert_eq!(samples, [sample]);
+    }
+
+    #[test]
+    fn abandoned_producer() {
+        let (producer, consumer) = ring_buffer(8);
+        assert!(!producer.is_abandoned());
+
+        drop(consumer);
+        assert!(producer.is_abandoned());
+    }
+
+    #[test]
+    fn concurrent_producer() {
+        const TOTAL: usize = 1 << 21;
+        let (mut producer, consumer) = ring_buffer(4096);
+
+        let producer_thread = thread::spawn(move || {
+            let mut next = 0;
+            // Callbacks come in varying sizes
+            for size in [64, 441, 512, 1000].iter().cycle() {
+                if next >= TOTAL {
+                    break;
+                }
+
+                let data: Vec<f64> = (next..TOTAL.min(next + size)).map(|x| x as f64).collect();
+                producer.push(&data);
+                next += data.len();
+            }
+        });
+
+        let mut samples = vec![0.0; 1024];
+        let mut last_written = 0;
+        while last_written < TOTAL {
+            let written = consumer.read_latest(&mut samples);
+            assert!(written >= last_written);
+
+            // Always the latest samples, in order, without any overwritten one
+            if written >= samples.len() {
+                for (i, sample) in samples.iter().enumerate() {
+                    assert_eq!(*sample as usize, written - samples.len() + i);
+                }
+            }
+
+            last_written = written;
+        }
+
+        producer_thread.join().unwrap();
+    }
+}
@@ -0,0 +1,134 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Number of pitch updates per second
+pub const DEFAULT_UPDATE_RATE: u32 = 25;
+pub const MAX_UPDATE_RATE: u32 = 100;
+
+// Runs the analysis at a fixed rate. The analysis windows are longer than the time between two
+// frames, so consecutive windows overlap
+#[derive(Clone, Debug)]
+pub struct FrameScheduler {
+    period: Duration,
+    // When the next frame is due
+    deadline: Instant,
+}
+
+// How the last frame went, reported in debug mode
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FrameTiming {
+    // Time left until the next frame
+    pub sleep: Duration,
+    // How long after its deadline the frame ended
+    pub late: Duration,
+    // Frames dropped because the last one ran late
+    pub skipped: u32,
+}
+
+impl FrameScheduler {
+    pub fn new(update_rate: u32) -> Self {
+        let period = Duration::from_secs_f64(1.0 / update_rate as f64);
+
+        Self {
+            period,
+            deadline: Instant::now() + period,
+        }
+    }
+
+    // Call once the frame is analysed, sleeps until the next one is due
+    pub fn wait(&mut self) -> FrameTiming {
+        let timing = self.schedule(Instant::now());
+        thread::sleep(timing.sleep);
+        timing
+    }
+
+    // When the analysis runs late, the frames which are already overdue are skipped instead of
+    // being run back to back, the display would lag further and further behind the input
+    fn schedule(&mut self, now: Instant) -> FrameTiming {
+        let late = now.saturating_duration_since(self.deadline);
+        let skipped = (late.as_secs_f64() / self.period.as_secs_f64()) as u32;
+
+        self.deadline += self.period * skipped;
+        let sleep = self.deadline.saturating_duration_since(now);
+        self.deadline += self.period;
+
+        FrameTiming {
+            sleep,
+            late,
+            skipped,
+        }
+    }
+}
+
+pub fn parse_update_rate(value: &str) -> Result<u32, String> {
+    let update_rate: u32 = value
+        .parse()
+        .map_err(|_| format!("{} is not a number of updates per second", value))?;
+
+    if !(1..=MAX_UPDATE_RATE).contains(&update_rate) {
+        return Err(format!(
+            "update rate must be between 1 and {} per second",
+            MAX_UPDATE_RATE
+        ));
+    }
+
+    Ok(update_rate)
+}
+
+#[cfg(test)]
+mod scheduler_tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn sleeps_until_next_frame() {
+        let mut scheduler = FrameScheduler::new(25);
+        let start = scheduler.deadline - 40 * MS;
+
+        let timing = scheduler.schedule(start + 15 * MS);
+
+        assert_eq!(timing.skipped, 0);
+        assert_eq!(timing.late, Duration::ZERO);
+        assert_eq!(timing.sleep, 25 * MS);
+        assert_eq!(scheduler.deadline, start + 80 * MS);
+    }
+
+    #[test]
+    fn runs_a_little_late_frame_right_away() {
+        let mut scheduler = FrameScheduler::new(25);
+        let start = scheduler.deadline - 40 * MS;
+
+        let timing = scheduler.schedule(start + 50 * MS);
+
+        assert_eq!(timing.skipped, 0);
+        assert_eq!(timing.sleep, Duration::ZERO);
+        // The next frames catch up with the schedule
+        assert_eq!(scheduler.deadline, start + 80 * MS);
+    }
+
+    #[test]
+    fn skips_overdue_frames() {
+        let mut scheduler = FrameScheduler::new(25);
+        let start = scheduler.deadline - 40 * MS;
+
+        // The analysis took 3 frames
+        let timing = scheduler.schedule(start + 130 * MS);
+
+        assert_eq!(timing.skipped, 2);
+        assert_eq!(timing.late, 90 * MS);
+        assert_eq!(timing.sleep, Duration::ZERO);
+        assert_eq!(scheduler.deadline, start + 160 * MS);
+
+        let timing = scheduler.schedule(start + 135 * MS);
+        assert_eq!(timing.sleep, 25 * MS);
+    }
+
+    #[test]
+    fn parse_update_rate_tests() {
+        assert_eq!(parse_update_rate("40"), Ok(40));
+        assert!(parse_update_rate("0").is_err());
+        assert!(parse_update_rate("1000").is_err());
+        assert!(parse_update_rate("fast").is_err());
+    }
+}
@@ -0,0 +1,256 @@
+use anyhow::{anyhow, Context, Result};
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use crate::guitar::{Note, Tuning};
+use crate::instrument::Instrument;
+
+// The tuning library is a plain text file, one tuning per line:
+//
+//   # Comments start with #
+//   Baritone: B3 F#3 D3 A2 E2 B1
+//
+// Notes go from the highest to the lowest string
+const HEADER: &str =
+    "# Custom tunings, one per line. Notes go from the highest to the lowest string
+# Baritone: B3 F#3 D3 A2 E2 B1
+";
+
+// $XDG_CONFIG_HOME/terminal-guitar-tuner/tunings.txt, falling back to ~/.config
+pub fn default_path() -> Option<PathBuf> {
+    let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(config_dir.join("terminal-guitar-tuner").join("tunings.txt"))
+}
+
+// A missing file is an empty library
+pub fn load(path: &Path) -> Result<Vec<Tuning>> {
+    match fs::read_to_string(path) {
+        Ok(content) => {
+            parse(&content).with_context(|| format!("Invalid tuning file {}", path.display()))
+        }
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(vec![]),
+        Err(error) => Err(error).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+pub fn parse(content: &str) -> Result<Vec<Tuning>> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            parse_line(line)
+                .map_err(|error| anyhow!("line {}: {}", index + 1, error))
+                .transpose()
+        })
+        .collect()
+}
+
+// Look up a tuning by name, user tunings take precedence over the built-in ones of the
+// instrument
+pub fn find(name: &str, library: &[Tuning], instrument: &Instrument) -> Result<Tuning> {
+    let tuning = library
+        .iter()
+        .chain(instrument.tunings.iter())
+        .find(|tuning| tuning.has_name(name))
+        .ok_or_else(|| {
+            let names: Vec<&str> = library
+                .iter()
+                .chain(instrument.tunings.iter())
+                .map(|tuning| tuning.name.as_str())
+                .collect();
+
+            anyhow!("Invalid tuning, expected one of: {}", names.join(", "))
+        })?;
+
+    instrument.check_tuning(tuning)?;
+    Ok(tuning.clone())
+}
+
+// Save the tuning in the library, replacing the tuning with the same name if any
+pub fn save(path: &Path, tuning: &Tuning) -> Result<()> {
+    if tuning.name.trim().is_empty() || tuning.name.contains([':', '\n']) {
+        return Err(anyhow!("Invalid tuning name {:?}", tuning.name));
+    }
+
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(error) if error.kind() == ErrorKind::NotFound => HEADER.to_string(),
+        Err(error) => {
+            return Err(error).with_context(|| format!("Failed to read {}", path.display()))
+        }
+    };
+
+    let line = format_tuning(tuning);
+    let mut replaced = false;
+
+    // Edit the file line by line to keep the comments
+    let mut lines: Vec<String> = content
+        .lines()
+        .map(|existing| match parse_line(existing) {
+            Ok(Some(existing_tuning)) if !replaced && existing_tuning.has_name(&tuning.name) => {
+                replaced = true;
+                line.clone()
+            }
+            _ => existing.to_string(),
+        })
+        .collect();
+
+    if !replaced {
+        lines.push(line);
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(path, lines.join("\n") + "\n")
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+// Returns None for blank and comment lines
+fn parse_line(line: &str) -> Result<Option<Tuning>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let (name, notes) = line
+        .split_once(':')
+        .ok_or_else(|| anyhow!("expected \"<name>: <notes>\""))?;
+
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(anyhow!("missing tuning name"));
+    }
+
+    let notes = notes
+        .split_whitespace()
+        .map(|note| note.parse::<Note>())
+        .collect::<Result<Vec<_>>>()?;
+
+    if notes.is_empty() {
+        return Err(anyhow!("tuning {:?} has no notes", name));
+    }
+
+    Ok(Some(Tuning {
+        name: name.to_string(),
+        notes,
+    }))
+}
+
+fn format_tuning(tuning: &Tuning) -> String {
+    let notes: Vec<String> = tuning.notes.iter().map(|note| note.notation()).collect();
+    format!("{}: {}", tuning.name.trim(), notes.join(" "))
+}
+
+#[cfg(test)]
+mod tuning_library_tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir()
+            .join(format!("tuning-library-{}-{}", name, std::process::id()))
+            .join("tunings.txt")
+    }
+
+    #[test]
+    fn parse_tunings() {
+        let content =
+            "# My tunings\n\nBaritone: B3 F#3 D3 A2 E2 B1\n  Nick Drake : C4 G3 C3 G2 C2 C2\n";
+        let result = parse(content).unwrap();
+
+        assert_eq!(
+            result,
+            vec![
+                Tuning::new("Baritone", &["B3", "F#3", "D3", "A2", "E2", "B1"]),
+                Tuning::new("Nick Drake", &["C4", "G3", "C3", "G2", "C2", "C2"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_error_has_line_number() {
+        let content = "Baritone: B3 F#3 D3 A2 E2 B1\n\nBroken: E4 B3 H3\n";
+        let error = parse(content).unwrap_err().to_string();
+
+        assert!(error.starts_with("line 3: "), "{}", error);
+        assert!(error.contains("H3"), "{}", error);
+    }
+
+    #[test]
+    fn parse_missing_separator() {
+        let error = parse("E4 B3 G3 D3 A2 E2").unwrap_err().to_string();
+        assert!(error.starts_with("line 1: "), "{}", error);
+    }
+
+    #[test]
+    fn parse_missing_notes() {
+        assert!(parse("Empty:").is_err());
+        assert!(parse(": E4").is_err());
+    }
+
+    #[test]
+    fn find_user_tuning_first() {
+        let library = vec![Tuning::new(
+            "Drop D",
+            &["D4", "A3", "F#3", "D3", "A2", "D2"],
+        )];
+        let guitar = Instrument::guitar();
+
+        assert_eq!(find("drop d", &library, &guitar).unwrap(), library[0]);
+        assert_eq!(find("Open G", &library, &guitar).unwrap().name, "Open G");
+        assert!(find("Unknown", &library, &guitar).is_err());
+    }
+
+    #[test]
+    fn find_instrument_tuning() {
+        let bass: Instrument = "bass".parse().unwrap();
+        let library = vec![Tuning::new(
+            "Baritone",
+            &["B3", "F#3", "D3", "A2", "E2", "B1"],
+        )];
+
+        assert_eq!(find("Standard", &library, &bass).unwrap().notes.len(), 4);
+        // Six strings tunings don't fit on a bass
+        assert!(find("Baritone", &library, &bass).is_err());
+        assert!(find("DADGAD", &library, &bass).is_err());
+    }
+
+    #[test]
+    fn load_missing_file() {
+        let result = load(&temp_path("missing")).unwrap();
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn save_and_load() {
+        let path = temp_path("save");
+        let _ = fs::remove_file(&path);
+
+        let baritone = Tuning::new("Baritone", &["B3", "F#3", "D3", "A2", "E2", "B1"]);
+        let open_c = Tuning::new("Open C", &["E4", "C4", "G3", "C3", "G2", "C2"]);
+        save(&path, &baritone).unwrap();
+        save(&path, &open_c).unwrap();
+
+        // Saving again with the same name replaces the tuning
+        let baritone = Tuning::new("baritone", &["Bb3", "F3", "Db3", "Ab2", "Eb2", "Bb1"]);
+        save(&path, &baritone).unwrap();
+
+        assert_eq!(load(&path).unwrap(), vec![baritone, open_c]);
+        assert!(fs::read_to_string(&path).unwrap().starts_with("# "));
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn save_invalid_name() {
+        let tuning = Tuning::new("A: B", &["E4"]);
+        assert!(save(&temp_path("invalid"), &tuning).is_err());
+    }
+}
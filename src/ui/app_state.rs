@@ -1,93 +1,375 @@
 use std::collections::HashSet;
 
-use super::{audio_graph, tuning_bar, tuning_notes};
-use crate::guitar::Note;
+use super::{
+    audio_graph, calibration, chromatic_note, device_picker, save_tuning, tuning_bar, tuning_notes,
+};
+use super::{IN_TUNE_DURATION, MIN_CLARITY, REFERENCE_PITCH_STEP};
+use crate::guitar::{
+    cents_difference, get_note_frequency, Note, Tuning, MAX_REFERENCE_PITCH, MIN_REFERENCE_PITCH,
+};
+use crate::instrument::Instrument;
+use crate::pitch_detector::{self, match_tuning_note, PitchEstimate, PitchTracker};
 use crate::AppEvent;
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Mode {
+    // Only detect the notes of the tuning strings
+    Strings,
+    // Detect any note
+    Chromatic,
+    // Derive the reference pitch from a note played by another instrument
+    Calibration,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct AppState {
+    pub mode: Mode,
+    pub instrument: Instrument,
+    pub detector_config: pitch_detector::Config,
     pub tuning_notes: tuning_notes::State,
+    pub chromatic_note: chromatic_note::State,
+    pub calibration: calibration::State,
+    pub save_tuning: save_tuning::State,
+    // Tuning waiting to be written to the tuning library
+    pub tuning_to_save: Option<Tuning>,
+    pub device_picker: device_picker::State,
+    // Device waiting to be recorded instead of the current one
+    pub device_to_select: Option<String>,
     pub tuning_bar: tuning_bar::State,
     pub audio_graph: audio_graph::State,
+    // Smooths the detected pitches before they reach the tuning bar
+    pub pitch_tracker: PitchTracker,
+    // Detections in a row in the accept range before a string is considered in tune
+    pub in_tune_detections: usize,
 }
 
 impl AppState {
-    pub fn new() -> Self {
+    pub fn new(
+        instrument: &Instrument,
+        tuning: &Tuning,
+        detector_config: pitch_detector::Config,
+        update_rate: u32,
+    ) -> Self {
         let tuning_notes_state = tuning_notes::State {
-            notes: vec![
-                Note::new("E4"),
-                Note::new("B3"),
-                Note::new("G3"),
-                Note::new("D3"),
-                Note::new("A2"),
-                Note::new("E2"),
-            ],
+            notes: tuning.notes.clone(),
             tuned_notes: HashSet::new(),
             selected_note_index: None,
             detecting_note: None,
         };
 
-        let tuning_bar_state = tuning_bar::State::new(&tuning_notes_state.notes[0]);
+        let tuning_bar_state = tuning_bar::State::new(
+            &tuning_notes_state.notes[0],
+            detector_config.reference_pitch,
+        );
         let audio_graph_state = audio_graph::State::new();
 
         AppState {
+            mode: Mode::Strings,
+            instrument: instrument.clone(),
+            detector_config,
             tuning_notes: tuning_notes_state,
+            chromatic_note: chromatic_note::State::new(),
+            calibration: calibration::State::new(),
+            save_tuning: save_tuning::State::new(),
+            tuning_to_save: None,
+            device_picker: device_picker::State::new(),
+            device_to_select: None,
             tuning_bar: tuning_bar_state,
             audio_graph: audio_graph_state,
+            pitch_tracker: PitchTracker::new(),
+            // At least twice, a single detection may be noise
+            in_tune_detections: ((IN_TUNE_DURATION * update_rate as f64).ceil() as usize).max(2),
         }
     }
 
     pub fn handle_event(&mut self, event: &AppEvent) {
         match event {
+            // While typing a tuning name, keys are text
+            AppEvent::CharacterTyped(character) => {
+                if let Some(name) = self.save_tuning.name.as_mut() {
+                    name.push(*character);
+                }
+            }
+            AppEvent::BackspacePressed => {
+                if let Some(name) = self.save_tuning.name.as_mut() {
+                    name.pop();
+                }
+            }
+
+            // While the device picker is open, keys move through the devices
+            AppEvent::DeviceButtonPressed => self.device_picker.open(),
+            AppEvent::UpButtonPressed if self.device_picker.is_open() => {
+                self.device_picker.prev_device()
+            }
+            AppEvent::DownButtonPressed if self.device_picker.is_open() => {
+                self.device_picker.next_device()
+            }
+            AppEvent::LeftButtonPressed
+            | AppEvent::RightButtonPressed
+            | AppEvent::SaveButtonPressed
+                if self.device_picker.is_open() => {}
+            AppEvent::EnterPressed if self.device_picker.is_open() => self.confirm_device(),
+            AppEvent::EscButtonPressed if self.device_picker.is_open() => {
+                self.device_picker.close();
+            }
+            AppEvent::DeviceStarted(name, devices) => {
+                if self.device_picker.current.is_some() {
+                    self.device_picker.message = Some(format!("Recording from {}", name));
+                }
+
+                self.device_picker.current = Some(name.clone());
+                self.device_picker.devices = devices.clone();
+            }
+            AppEvent::DeviceFailed(error) => {
+                self.device_picker.message = Some(format!("Failed to switch device: {}", error));
+            }
+
+            AppEvent::EnterPressed => self.confirm_save_tuning(),
+            AppEvent::EscButtonPressed if self.save_tuning.name.is_some() => {
+                self.save_tuning.name = None
+            }
+            AppEvent::SaveButtonPressed if self.mode == Mode::Strings => {
+                self.save_tuning.name = Some(String::new());
+                self.save_tuning.message = None;
+            }
+            AppEvent::SaveButtonPressed => (),
+
+            // The reference note is the only selectable note while calibrating
+            AppEvent::RightButtonPressed if self.mode == Mode::Calibration => {
+                self.calibration.next_note();
+                self.reset_tuning_bar();
+            }
+            AppEvent::LeftButtonPressed if self.mode == Mode::Calibration => {
+                self.calibration.prev_note();
+                self.reset_tuning_bar();
+            }
+            AppEvent::EscButtonPressed if self.mode == Mode::Calibration => {
+                self.set_mode(Mode::Strings)
+            }
+            AppEvent::UpButtonPressed | AppEvent::DownButtonPressed
+                if self.mode == Mode::Calibration => {}
+
             AppEvent::UpButtonPressed => self.tuning_notes.prev_string(),
             AppEvent::DownButtonPressed => self.tuning_notes.next_string(),
             AppEvent::RightButtonPressed => self.tuning_notes.next_note(),
             AppEvent::LeftButtonPressed => self.tuning_notes.prev_note(),
             AppEvent::EscButtonPressed => self.tuning_notes.selected_note_index = None,
+            AppEvent::ChromaticButtonPressed => self.toggle_mode(Mode::Chromatic),
+            AppEvent::CalibrationButtonPressed => self.toggle_mode(Mode::Calibration),
+            AppEvent::PlusButtonPressed => self.adjust_reference_pitch(REFERENCE_PITCH_STEP),
+            AppEvent::MinusButtonPressed => self.adjust_reference_pitch(-REFERENCE_PITCH_STEP),
 
             // To protect against accidental noise, a string is considered in tune if
-            // the detected pitch stays in the accept_range two times in a row
-            AppEvent::PitchDetected(note, frequency) => {
-                if !self.tuning_notes.notes.contains(&note) {
+            // the detected pitch stays in the accept_range for IN_TUNE_DURATION
+            AppEvent::PitchDetected(note, frequency) if self.mode == Mode::Strings => {
+                if !self.tuning_notes.notes.contains(note) {
                     return;
                 }
 
                 if self.tuning_notes.detecting_note != Some(*note) {
                     self.tuning_notes.detecting_note = Some(*note);
-                    self.tuning_bar = tuning_bar::State::new(note);
+                    self.tuning_bar = self.new_tuning_bar(note);
                 }
 
                 self.tuning_bar.current_pitch = Some(*frequency);
 
                 if self.tuning_bar.in_tune_range(*frequency) {
-                    if self.tuning_bar.pitch_in_accept_range_once {
+                    self.tuning_bar.detections_in_accept_range += 1;
+
+                    if self.tuning_bar.detections_in_accept_range >= self.in_tune_detections {
                         self.tuning_notes.tuned_notes.insert(*note);
-                    } else {
-                        self.tuning_bar.pitch_in_accept_range_once = true;
                     }
                 } else {
-                    self.tuning_bar.pitch_in_accept_range_once = false;
+                    self.tuning_bar.detections_in_accept_range = 0;
                 }
             }
 
+            // Pitches of the chromatic and calibration modes only come from estimates
+            AppEvent::PitchDetected(..) => (),
+
+            // Low clarity frames are most likely noise, ignore them instead of jumping
+            // between notes
+            AppEvent::PitchEstimated(estimate) if estimate.clarity < MIN_CLARITY => (),
+
+            // The new pluck is measured
+            AppEvent::PitchEstimated(_) if self.tuning_bar.plucked => {
+                self.tuning_bar.plucked = false;
+                self.handle_event(event);
+            }
+
+            AppEvent::PitchEstimated(estimate) => match self.mode {
+                Mode::Strings => {
+                    let event = match match_tuning_note(
+                        estimate,
+                        &self.tuning_notes.notes,
+                        &self.detector_config,
+                    ) {
+                        Some((note, frequency)) => {
+                            let (note, frequency) = self.pitch_tracker.update(
+                                note,
+                                frequency,
+                                self.detector_config.reference_pitch,
+                            );
+                            AppEvent::PitchDetected(note, frequency)
+                        }
+                        // A single frame out of the tuning in the middle of a note is an outlier,
+                        // the note is kept
+                        None if self.pitch_tracker.miss() => return,
+                        None => AppEvent::NoPitchDetected,
+                    };
+
+                    self.handle_event(&event);
+                }
+
+                Mode::Chromatic => {
+                    let (note, frequency) = self.pitch_tracker.update(
+                        estimate.note,
+                        estimate.frequency,
+                        self.detector_config.reference_pitch,
+                    );
+
+                    if self.chromatic_note.estimate.map(|e| e.note) != Some(note) {
+                        self.tuning_bar = self.new_tuning_bar(&note);
+                    }
+
+                    self.chromatic_note.estimate = Some(PitchEstimate {
+                        frequency,
+                        note,
+                        cents: cents_difference(
+                            frequency,
+                            get_note_frequency(&note, self.detector_config.reference_pitch),
+                        ),
+                        ..*estimate
+                    });
+                    self.tuning_bar.current_pitch = Some(frequency);
+                }
+
+                Mode::Calibration => match self.calibration.add_detection(estimate.frequency) {
+                    Some(reference_pitch) => {
+                        self.set_reference_pitch(reference_pitch);
+                        self.set_mode(Mode::Strings);
+                    }
+
+                    None => self.tuning_bar.current_pitch = Some(estimate.frequency),
+                },
+            },
+
             AppEvent::NoPitchDetected => {
+                self.pitch_tracker.reset();
                 self.tuning_notes.detecting_note = None;
-                self.tuning_bar = tuning_bar::State::new(&self.tuning_notes.notes[0]);
+                self.chromatic_note = chromatic_note::State::new();
+                self.reset_tuning_bar();
+            }
+
+            // Keep showing the last pitch while the attack of the new pluck is skipped
+            // The new pluck may be another string, it doesn't have to wait for the hysteresis
+            AppEvent::PluckDetected => {
+                self.pitch_tracker.reset();
+                self.tuning_bar.plucked = true;
+                self.tuning_bar.silence = None;
+            }
+
+            // Nobody is playing, show it instead of a note
+            AppEvent::SilenceDetected(level) => {
+                self.handle_event(&AppEvent::NoPitchDetected);
+                self.tuning_bar.silence = Some(*level);
             }
 
             AppEvent::AudioRecorded(data) => self.audio_graph.dataset = data.clone(),
             AppEvent::Quit => (),
         }
     }
+
+    fn confirm_save_tuning(&mut self) {
+        let name = match self.save_tuning.name.as_ref() {
+            Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+            _ => return,
+        };
+
+        self.tuning_to_save = Some(Tuning {
+            name,
+            notes: self.tuning_notes.notes.clone(),
+        });
+        self.save_tuning.name = None;
+    }
+
+    fn confirm_device(&mut self) {
+        match self.device_picker.close() {
+            Some(device) if Some(&device) != self.device_picker.current.as_ref() => {
+                self.device_picker.message = Some(format!("Switching to {}...", device));
+                self.device_to_select = Some(device);
+            }
+            _ => (),
+        }
+    }
+
+    // Switch to the mode, or back to the strings mode if it's already active
+    fn toggle_mode(&mut self, mode: Mode) {
+        if self.mode == mode {
+            self.set_mode(Mode::Strings);
+        } else {
+            self.set_mode(mode);
+        }
+    }
+
+    fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+        self.pitch_tracker.reset();
+        self.tuning_notes.detecting_note = None;
+        self.tuning_notes.selected_note_index = None;
+        self.chromatic_note = chromatic_note::State::new();
+        self.calibration.detections.clear();
+        self.reset_tuning_bar();
+    }
+
+    fn adjust_reference_pitch(&mut self, amount: f64) {
+        self.set_reference_pitch(self.detector_config.reference_pitch + amount);
+    }
+
+    fn set_reference_pitch(&mut self, reference_pitch: f64) {
+        self.detector_config.reference_pitch =
+            reference_pitch.clamp(MIN_REFERENCE_PITCH, MAX_REFERENCE_PITCH);
+
+        // Strings tuned against the previous reference pitch need to be tuned again
+        self.tuning_notes.tuned_notes.clear();
+        self.pitch_tracker.reset();
+        self.tuning_notes.detecting_note = None;
+        self.chromatic_note = chromatic_note::State::new();
+        self.reset_tuning_bar();
+    }
+
+    // While nothing is detected, the tuning bar shows the first string, or the
+    // reference note when calibrating
+    fn reset_tuning_bar(&mut self) {
+        let note = match self.mode {
+            Mode::Calibration => self.calibration.note,
+            Mode::Strings | Mode::Chromatic => self.tuning_notes.notes[0],
+        };
+
+        self.tuning_bar = self.new_tuning_bar(&note);
+    }
+
+    fn new_tuning_bar(&self, note: &Note) -> tuning_bar::State {
+        tuning_bar::State::new(note, self.detector_config.reference_pitch)
+    }
 }
 
 #[cfg(test)]
 mod handle_event_tests {
     use super::*;
+    use crate::guitar::{get_note_frequency, nearest_note, A4_FREQUENCY};
+    use crate::pitch_detector::{Candidate, Config, Level, PitchEstimate};
+    use crate::scheduler::DEFAULT_UPDATE_RATE;
+    use crate::ui::CALIBRATION_DETECTIONS;
 
     #[test]
     fn up_button_pressed_no_selected_string() {
-        let mut state = AppState::new();
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
         state.tuning_notes.notes = vec![Note::new("E4"), Note::new("F4")];
         state.tuning_notes.selected_note_index = None;
 
@@ -98,7 +380,12 @@ mod handle_event_tests {
 
     #[test]
     fn up_button_pressed_with_selected_string() {
-        let mut state = AppState::new();
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
         state.tuning_notes.notes = vec![Note::new("E4"), Note::new("F4")];
         state.tuning_notes.selected_note_index = Some(1);
 
@@ -109,7 +396,12 @@ mod handle_event_tests {
 
     #[test]
     fn down_button_pressed_no_selected_string() {
-        let mut state = AppState::new();
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
         state.tuning_notes.notes = vec![Note::new("E4"), Note::new("F4")];
         state.tuning_notes.selected_note_index = None;
 
@@ -120,7 +412,12 @@ mod handle_event_tests {
 
     #[test]
     fn down_button_pressed_with_selected_string() {
-        let mut state = AppState::new();
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
         state.tuning_notes.notes = vec![Note::new("E4"), Note::new("F4")];
         state.tuning_notes.selected_note_index = Some(0);
 
@@ -131,7 +428,12 @@ mod handle_event_tests {
 
     #[test]
     fn left_button_pressed_no_selected_string() {
-        let mut state = AppState::new();
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
         state.tuning_notes.notes = vec![Note::new("E4"), Note::new("F4")];
         state.tuning_notes.selected_note_index = None;
         let clone = state.clone();
@@ -143,7 +445,12 @@ mod handle_event_tests {
 
     #[test]
     fn left_button_pressed_with_selected_string() {
-        let mut state = AppState::new();
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
         state.tuning_notes.notes = vec![Note::new("E4"), Note::new("F4")];
         state.tuning_notes.selected_note_index = Some(0);
 
@@ -157,7 +464,12 @@ mod handle_event_tests {
 
     #[test]
     fn right_button_pressed_no_selected_string() {
-        let mut state = AppState::new();
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
         state.tuning_notes.notes = vec![Note::new("E4"), Note::new("F4")];
         state.tuning_notes.selected_note_index = None;
         let clone = state.clone();
@@ -169,7 +481,12 @@ mod handle_event_tests {
 
     #[test]
     fn right_button_pressed_with_selected_string() {
-        let mut state = AppState::new();
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
         state.tuning_notes.notes = vec![Note::new("E4"), Note::new("F4")];
         state.tuning_notes.selected_note_index = Some(0);
 
@@ -183,7 +500,12 @@ mod handle_event_tests {
 
     #[test]
     fn pitch_detected_note_exists() {
-        let mut state = AppState::new();
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
         state.tuning_notes.notes = vec![Note::new("E4"), Note::new("F4")];
         state.tuning_notes.selected_note_index = Some(0);
 
@@ -197,7 +519,12 @@ mod handle_event_tests {
 
     #[test]
     fn pitch_detected_note_does_not_exist() {
-        let mut state = AppState::new();
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
         state.tuning_notes.notes = vec![Note::new("E4"), Note::new("F4")];
         state.tuning_notes.selected_note_index = Some(0);
 
@@ -210,7 +537,12 @@ mod handle_event_tests {
 
     #[test]
     fn pitch_detected_note_once() {
-        let mut state = AppState::new();
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
         state.tuning_notes.notes = vec![Note::new("E4"), Note::new("F4")];
         state.tuning_notes.selected_note_index = Some(0);
 
@@ -218,13 +550,19 @@ mod handle_event_tests {
         let pitch = 329.0;
         state.handle_event(&AppEvent::PitchDetected(note, pitch));
 
-        assert_eq!(state.tuning_notes.tuned_notes.contains(&note), false);
+        assert!(!state.tuning_notes.tuned_notes.contains(&note));
         assert_eq!(state.tuning_bar.current_pitch, Some(pitch));
     }
 
     #[test]
     fn pitch_detected_note_twice() {
-        let mut state = AppState::new();
+        // Two updates per second
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            2,
+        );
         state.tuning_notes.notes = vec![Note::new("E4"), Note::new("F4")];
         state.tuning_notes.selected_note_index = Some(0);
 
@@ -233,13 +571,40 @@ mod handle_event_tests {
         state.handle_event(&AppEvent::PitchDetected(note, pitch));
         state.handle_event(&AppEvent::PitchDetected(note, pitch));
 
-        assert_eq!(state.tuning_notes.tuned_notes.contains(&note), true);
+        assert!(state.tuning_notes.tuned_notes.contains(&note));
         assert_eq!(state.tuning_bar.current_pitch, Some(pitch));
     }
 
+    #[test]
+    fn pitch_detected_note_for_a_second() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+        state.tuning_notes.notes = vec![Note::new("E4"), Note::new("F4")];
+        state.tuning_notes.selected_note_index = Some(0);
+
+        let note = Note::new("E4");
+        let pitch = 329.0;
+        for _ in 1..DEFAULT_UPDATE_RATE {
+            state.handle_event(&AppEvent::PitchDetected(note, pitch));
+        }
+        assert!(!state.tuning_notes.tuned_notes.contains(&note));
+
+        state.handle_event(&AppEvent::PitchDetected(note, pitch));
+        assert!(state.tuning_notes.tuned_notes.contains(&note));
+    }
+
     #[test]
     fn pitch_detected_note_switch_between_notes() {
-        let mut state = AppState::new();
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
         state.tuning_notes.notes = vec![Note::new("E4"), Note::new("F4")];
         state.tuning_notes.selected_note_index = Some(0);
 
@@ -249,13 +614,18 @@ mod handle_event_tests {
         state.handle_event(&AppEvent::PitchDetected(Note::new("F4"), 349.0));
         state.handle_event(&AppEvent::PitchDetected(note, pitch));
 
-        assert_eq!(state.tuning_notes.tuned_notes.contains(&note), false);
+        assert!(!state.tuning_notes.tuned_notes.contains(&note));
         assert_eq!(state.tuning_bar.current_pitch, Some(pitch));
     }
 
     #[test]
     fn pitch_detected_note_interrupt_by_no_pitch() {
-        let mut state = AppState::new();
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
         state.tuning_notes.notes = vec![Note::new("E4"), Note::new("F4")];
         state.tuning_notes.selected_note_index = Some(0);
 
@@ -265,16 +635,21 @@ mod handle_event_tests {
         state.handle_event(&AppEvent::NoPitchDetected);
         state.handle_event(&AppEvent::PitchDetected(note, pitch));
 
-        assert_eq!(state.tuning_notes.tuned_notes.contains(&note), false);
+        assert!(!state.tuning_notes.tuned_notes.contains(&note));
         assert_eq!(state.tuning_bar.current_pitch, Some(pitch));
     }
 
     #[test]
     fn no_pitch_detected() {
-        let mut state = AppState::new();
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
         state.tuning_notes.notes = vec![Note::new("E4"), Note::new("F4")];
         state.tuning_notes.selected_note_index = Some(0);
-        state.tuning_notes.detecting_note = Some(state.tuning_notes.notes[0].clone());
+        state.tuning_notes.detecting_note = Some(state.tuning_notes.notes[0]);
         state.tuning_bar.current_pitch = Some(100.0);
 
         state.handle_event(&AppEvent::NoPitchDetected);
@@ -282,4 +657,650 @@ mod handle_event_tests {
         assert_eq!(state.tuning_notes.detecting_note, None);
         assert_eq!(state.tuning_bar.current_pitch, None);
     }
+
+    #[test]
+    fn silence_detected() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+        let level = Level {
+            rms: -72.0,
+            peak: -65.0,
+        };
+        state.handle_event(&AppEvent::PitchEstimated(estimate(329.0, 0.9)));
+
+        state.handle_event(&AppEvent::SilenceDetected(level));
+
+        assert_eq!(state.tuning_notes.detecting_note, None);
+        assert_eq!(state.tuning_bar.current_pitch, None);
+        assert_eq!(state.tuning_bar.silence, Some(level));
+
+        // Playing again
+        state.handle_event(&AppEvent::PitchEstimated(estimate(329.0, 0.9)));
+        assert_eq!(state.tuning_bar.silence, None);
+        assert_eq!(state.tuning_bar.current_pitch, Some(329.0));
+    }
+
+    #[test]
+    fn pluck_detected() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+        state.handle_event(&AppEvent::PitchEstimated(estimate(329.0, 0.9)));
+
+        state.handle_event(&AppEvent::PluckDetected);
+
+        // The previous pitch stays until the new pluck is measured
+        assert!(state.tuning_bar.plucked);
+        assert_eq!(state.tuning_bar.current_pitch, Some(329.0));
+
+        // Noise doesn't count as a measure
+        state.handle_event(&AppEvent::PitchEstimated(estimate(300.0, 0.2)));
+        assert!(state.tuning_bar.plucked);
+
+        state.handle_event(&AppEvent::PitchEstimated(estimate(330.0, 0.9)));
+        assert!(!state.tuning_bar.plucked);
+        assert_eq!(state.tuning_bar.current_pitch, Some(330.0));
+    }
+
+    #[test]
+    fn silence_detected_in_chromatic_mode() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+        state.mode = Mode::Chromatic;
+        state.handle_event(&AppEvent::PitchEstimated(estimate(555.0, 0.9)));
+
+        state.handle_event(&AppEvent::SilenceDetected(Level {
+            rms: f64::NEG_INFINITY,
+            peak: f64::NEG_INFINITY,
+        }));
+
+        assert_eq!(state.chromatic_note.estimate, None);
+        assert!(state.tuning_bar.silence.is_some());
+    }
+
+    #[test]
+    fn chromatic_button_pressed() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+        state.tuning_notes.selected_note_index = Some(0);
+
+        state.handle_event(&AppEvent::ChromaticButtonPressed);
+        assert_eq!(state.mode, Mode::Chromatic);
+        assert_eq!(state.tuning_notes.selected_note_index, None);
+
+        state.handle_event(&AppEvent::ChromaticButtonPressed);
+        assert_eq!(state.mode, Mode::Strings);
+    }
+
+    fn estimate(frequency: f64, clarity: f64) -> PitchEstimate {
+        let (note, cents) = nearest_note(frequency, A4_FREQUENCY);
+
+        PitchEstimate {
+            frequency,
+            note,
+            cents,
+            clarity,
+            rms: 0.1,
+            // Only the frequency itself is supported by the spectrum
+            candidates: std::array::from_fn(|index| Candidate {
+                frequency: frequency / (index + 1) as f64,
+                score: if index == 0 { 1.0 } else { 0.0 },
+            }),
+        }
+    }
+
+    #[test]
+    fn pitch_estimated_in_chromatic_mode() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+        state.mode = Mode::Chromatic;
+
+        let pitch = estimate(555.0, 0.9);
+        state.handle_event(&AppEvent::PitchEstimated(pitch));
+
+        assert_eq!(state.chromatic_note.estimate, Some(pitch));
+        assert_eq!(
+            state.tuning_bar.center,
+            get_note_frequency(&Note::new("C#5"), A4_FREQUENCY)
+        );
+        assert_eq!(state.tuning_bar.current_pitch, Some(555.0));
+        assert_eq!(state.tuning_notes.detecting_note, None);
+    }
+
+    #[test]
+    fn pitch_estimated_in_strings_mode() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+
+        state.handle_event(&AppEvent::PitchEstimated(estimate(329.0, 0.9)));
+
+        assert_eq!(state.tuning_notes.detecting_note, Some(Note::new("E4")));
+        assert_eq!(state.tuning_bar.current_pitch, Some(329.0));
+        assert_eq!(state.chromatic_note.estimate, None);
+    }
+
+    #[test]
+    fn pitch_estimated_matches_alternative_tuning() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+        state.tuning_notes.notes[5] = Note::new("D2");
+
+        state.handle_event(&AppEvent::PitchEstimated(estimate(73.0, 0.9)));
+
+        assert_eq!(state.tuning_notes.detecting_note, Some(Note::new("D2")));
+    }
+
+    #[test]
+    fn pitch_estimated_does_not_match_tuning() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+        state.handle_event(&AppEvent::PitchEstimated(estimate(329.0, 0.9)));
+
+        // Unmatched frames in a row, the string is not played anymore
+        for _ in 0..3 {
+            state.handle_event(&AppEvent::PitchEstimated(estimate(700.0, 0.9)));
+        }
+
+        assert_eq!(state.tuning_notes.detecting_note, None);
+        assert_eq!(state.tuning_bar.current_pitch, None);
+    }
+
+    #[test]
+    fn pitch_estimated_single_unmatched_frame() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+        state.handle_event(&AppEvent::PitchEstimated(estimate(329.0, 0.9)));
+        state.handle_event(&AppEvent::PitchEstimated(estimate(329.0, 0.9)));
+        let current_pitch = state.tuning_bar.current_pitch;
+
+        state.handle_event(&AppEvent::PitchEstimated(estimate(700.0, 0.9)));
+
+        assert_eq!(state.tuning_notes.detecting_note, Some(Note::new("E4")));
+        assert_eq!(state.tuning_bar.current_pitch, current_pitch);
+
+        state.handle_event(&AppEvent::PitchEstimated(estimate(329.0, 0.9)));
+
+        assert_eq!(state.tuning_notes.detecting_note, Some(Note::new("E4")));
+        assert_eq!(state.tuning_bar.current_pitch, Some(329.0));
+    }
+
+    #[test]
+    fn pitch_estimated_waits_before_switching_string() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+        state.handle_event(&AppEvent::PitchEstimated(estimate(329.0, 0.9)));
+
+        // A single frame of another string is most likely a harmonic
+        state.handle_event(&AppEvent::PitchEstimated(estimate(247.0, 0.9)));
+        assert_eq!(state.tuning_notes.detecting_note, Some(Note::new("E4")));
+
+        state.handle_event(&AppEvent::PitchEstimated(estimate(247.0, 0.9)));
+        assert_eq!(state.tuning_notes.detecting_note, Some(Note::new("B3")));
+        assert_eq!(state.tuning_bar.current_pitch, Some(247.0));
+    }
+
+    #[test]
+    fn pitch_estimated_is_smoothed() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+        state.handle_event(&AppEvent::PitchEstimated(estimate(329.0, 0.9)));
+        state.handle_event(&AppEvent::PitchEstimated(estimate(331.0, 0.9)));
+
+        let current_pitch = state.tuning_bar.current_pitch.unwrap();
+        assert!(329.0 < current_pitch && current_pitch < 331.0);
+    }
+
+    #[test]
+    fn pitch_estimated_low_clarity() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+        state.handle_event(&AppEvent::PitchEstimated(estimate(329.0, 0.9)));
+        let clone = state.clone();
+
+        state.handle_event(&AppEvent::PitchEstimated(estimate(247.0, 0.3)));
+
+        assert_eq!(state, clone);
+    }
+
+    #[test]
+    fn pitch_detected_in_chromatic_mode() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+        state.mode = Mode::Chromatic;
+        let clone = state.clone();
+
+        state.handle_event(&AppEvent::PitchDetected(Note::new("E4"), 329.0));
+
+        assert_eq!(state, clone);
+    }
+
+    #[test]
+    fn no_pitch_detected_in_chromatic_mode() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+        state.mode = Mode::Chromatic;
+
+        state.handle_event(&AppEvent::PitchEstimated(estimate(555.0, 0.9)));
+        state.handle_event(&AppEvent::NoPitchDetected);
+
+        assert_eq!(state.chromatic_note.estimate, None);
+        assert_eq!(state.tuning_bar.current_pitch, None);
+    }
+
+    #[test]
+    fn plus_button_pressed() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+        state.tuning_notes.tuned_notes.insert(Note::new("E4"));
+
+        state.handle_event(&AppEvent::PlusButtonPressed);
+        state.handle_event(&AppEvent::PlusButtonPressed);
+
+        assert_eq!(state.detector_config.reference_pitch, 442.0);
+        assert_eq!(state.tuning_bar.reference_pitch, 442.0);
+        assert!(state.tuning_notes.tuned_notes.is_empty());
+    }
+
+    #[test]
+    fn minus_button_pressed() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+
+        state.handle_event(&AppEvent::MinusButtonPressed);
+
+        assert_eq!(state.detector_config.reference_pitch, 439.0);
+        assert_eq!(
+            state.tuning_bar.center,
+            get_note_frequency(&Note::new("E4"), 439.0)
+        );
+    }
+
+    #[test]
+    fn reference_pitch_is_bounded() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+        state.detector_config.reference_pitch = MAX_REFERENCE_PITCH;
+
+        state.handle_event(&AppEvent::PlusButtonPressed);
+
+        assert_eq!(state.detector_config.reference_pitch, MAX_REFERENCE_PITCH);
+    }
+
+    #[test]
+    fn pitch_detected_with_reference_pitch() {
+        // Two updates per second
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config {
+                reference_pitch: 432.0,
+                ..Config::default()
+            },
+            2,
+        );
+
+        state.handle_event(&AppEvent::PitchEstimated(estimate(108.0, 0.9)));
+        state.handle_event(&AppEvent::PitchEstimated(estimate(108.0, 0.9)));
+
+        assert_eq!(state.tuning_notes.detecting_note, Some(Note::new("A2")));
+        assert!(state.tuning_notes.tuned_notes.contains(&Note::new("A2")));
+    }
+
+    #[test]
+    fn calibration_button_pressed() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+
+        state.handle_event(&AppEvent::CalibrationButtonPressed);
+        assert_eq!(state.mode, Mode::Calibration);
+        assert_eq!(state.tuning_bar.center, A4_FREQUENCY);
+
+        state.handle_event(&AppEvent::CalibrationButtonPressed);
+        assert_eq!(state.mode, Mode::Strings);
+    }
+
+    #[test]
+    fn calibration_change_reference_note() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+        state.handle_event(&AppEvent::CalibrationButtonPressed);
+
+        state.handle_event(&AppEvent::LeftButtonPressed);
+
+        assert_eq!(state.calibration.note, Note::new("Ab4"));
+        assert_eq!(state.tuning_notes.selected_note_index, None);
+        assert_eq!(
+            state.tuning_bar.center,
+            get_note_frequency(&Note::new("Ab4"), A4_FREQUENCY)
+        );
+    }
+
+    #[test]
+    fn calibration_sets_reference_pitch() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+        state.tuning_notes.tuned_notes.insert(Note::new("E4"));
+        state.handle_event(&AppEvent::CalibrationButtonPressed);
+
+        for _ in 0..CALIBRATION_DETECTIONS {
+            state.handle_event(&AppEvent::PitchEstimated(estimate(442.0, 0.9)));
+        }
+
+        assert_eq!(state.mode, Mode::Strings);
+        assert!((state.detector_config.reference_pitch - 442.0).abs() < 1e-9);
+        assert!(state.tuning_notes.tuned_notes.is_empty());
+    }
+
+    #[test]
+    fn calibration_ignores_low_clarity() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+        state.handle_event(&AppEvent::CalibrationButtonPressed);
+
+        for _ in 0..CALIBRATION_DETECTIONS {
+            state.handle_event(&AppEvent::PitchEstimated(estimate(442.0, 0.5)));
+        }
+
+        assert_eq!(state.mode, Mode::Calibration);
+        assert_eq!(state.detector_config.reference_pitch, A4_FREQUENCY);
+    }
+
+    #[test]
+    fn calibration_cancelled() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+        state.handle_event(&AppEvent::CalibrationButtonPressed);
+        state.handle_event(&AppEvent::PitchEstimated(estimate(442.0, 0.9)));
+
+        state.handle_event(&AppEvent::EscButtonPressed);
+
+        assert_eq!(state.mode, Mode::Strings);
+        assert!(state.calibration.detections.is_empty());
+        assert_eq!(state.detector_config.reference_pitch, A4_FREQUENCY);
+    }
+
+    #[test]
+    fn new_with_tuning() {
+        let tuning: Tuning = "Drop D".parse().unwrap();
+        let state = AppState::new(
+            &Instrument::guitar(),
+            &tuning,
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+
+        assert_eq!(state.tuning_notes.notes, tuning.notes);
+        assert_eq!(
+            state.tuning_bar.center,
+            get_note_frequency(&Note::new("E4"), A4_FREQUENCY)
+        );
+    }
+
+    #[test]
+    fn save_tuning() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+        state.handle_event(&AppEvent::RightButtonPressed);
+        state.tuning_notes.notes[5] = Note::new("D2");
+
+        state.handle_event(&AppEvent::SaveButtonPressed);
+        for character in "Drop Dx".chars() {
+            state.handle_event(&AppEvent::CharacterTyped(character));
+        }
+        state.handle_event(&AppEvent::BackspacePressed);
+        state.handle_event(&AppEvent::EnterPressed);
+
+        assert_eq!(state.save_tuning.name, None);
+        assert_eq!(
+            state.tuning_to_save,
+            Some(Tuning {
+                name: "Drop D".to_string(),
+                notes: state.tuning_notes.notes.clone(),
+            })
+        );
+    }
+
+    #[test]
+    fn save_tuning_with_empty_name() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+
+        state.handle_event(&AppEvent::SaveButtonPressed);
+        state.handle_event(&AppEvent::CharacterTyped(' '));
+        state.handle_event(&AppEvent::EnterPressed);
+
+        assert_eq!(state.save_tuning.name, Some(" ".to_string()));
+        assert_eq!(state.tuning_to_save, None);
+    }
+
+    #[test]
+    fn save_tuning_cancelled() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+        state.tuning_notes.selected_note_index = Some(0);
+
+        state.handle_event(&AppEvent::SaveButtonPressed);
+        state.handle_event(&AppEvent::CharacterTyped('a'));
+        state.handle_event(&AppEvent::EscButtonPressed);
+
+        assert_eq!(state.save_tuning.name, None);
+        assert_eq!(state.tuning_to_save, None);
+        // Esc only closes the prompt
+        assert_eq!(state.tuning_notes.selected_note_index, Some(0));
+    }
+
+    #[test]
+    fn new_with_instrument() {
+        let ukulele: Instrument = "ukulele".parse().unwrap();
+        let state = AppState::new(
+            &ukulele,
+            ukulele.default_tuning(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+
+        assert_eq!(state.tuning_notes.notes.len(), 4);
+        assert_eq!(state.tuning_bar.center, A4_FREQUENCY);
+    }
+
+    fn state_with_devices() -> AppState {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+        let devices = vec![
+            "default".to_string(),
+            "Built-in Microphone".to_string(),
+            "Scarlett 2i2 USB".to_string(),
+        ];
+        state.handle_event(&AppEvent::DeviceStarted("default".to_string(), devices));
+        state
+    }
+
+    #[test]
+    fn select_device() {
+        let mut state = state_with_devices();
+        assert_eq!(state.device_picker.message, None);
+
+        state.handle_event(&AppEvent::DeviceButtonPressed);
+        assert_eq!(state.device_picker.selected, Some(0));
+
+        state.handle_event(&AppEvent::UpButtonPressed);
+        state.handle_event(&AppEvent::EnterPressed);
+
+        assert!(!state.device_picker.is_open());
+        assert_eq!(state.device_to_select, Some("Scarlett 2i2 USB".to_string()));
+        // The strings aren't selected while picking a device
+        assert_eq!(state.tuning_notes.selected_note_index, None);
+
+        state.device_to_select = None;
+        state.handle_event(&AppEvent::DeviceStarted(
+            "Scarlett 2i2 USB".to_string(),
+            state.device_picker.devices.clone(),
+        ));
+
+        assert_eq!(
+            state.device_picker.current,
+            Some("Scarlett 2i2 USB".to_string())
+        );
+        assert_eq!(
+            state.device_picker.message,
+            Some("Recording from Scarlett 2i2 USB".to_string())
+        );
+    }
+
+    #[test]
+    fn select_current_device() {
+        let mut state = state_with_devices();
+
+        state.handle_event(&AppEvent::DeviceButtonPressed);
+        state.handle_event(&AppEvent::EnterPressed);
+
+        assert_eq!(state.device_to_select, None);
+        assert_eq!(state.device_picker.message, None);
+    }
+
+    #[test]
+    fn select_device_cancelled() {
+        let mut state = state_with_devices();
+
+        state.handle_event(&AppEvent::DeviceButtonPressed);
+        state.handle_event(&AppEvent::DownButtonPressed);
+        state.handle_event(&AppEvent::EscButtonPressed);
+
+        assert!(!state.device_picker.is_open());
+        assert_eq!(state.device_to_select, None);
+    }
+
+    #[test]
+    fn select_device_failed() {
+        let mut state = state_with_devices();
+
+        state.handle_event(&AppEvent::DeviceFailed("Device busy".to_string()));
+
+        assert_eq!(state.device_picker.current, Some("default".to_string()));
+        assert_eq!(
+            state.device_picker.message,
+            Some("Failed to switch device: Device busy".to_string())
+        );
+    }
+
+    #[test]
+    fn select_device_without_device() {
+        let mut state = AppState::new(
+            &Instrument::guitar(),
+            &Tuning::standard(),
+            Config::default(),
+            DEFAULT_UPDATE_RATE,
+        );
+
+        state.handle_event(&AppEvent::DeviceButtonPressed);
+
+        assert!(!state.device_picker.is_open());
+        assert_eq!(
+            state.device_picker.message,
+            Some("Not recording from an input device".to_string())
+        );
+    }
 }
@@ -83,11 +83,10 @@ impl StatefulWidget for AudioGraph {
 }
 
 fn normalize(dataset: &[f64]) -> Vec<(f64, f64)> {
-    let max = dataset
+    let max = *dataset
         .iter()
-        .max_by(|m1, m2| f64::total_cmp(&m1, &m2))
-        .unwrap()
-        .clone();
+        .max_by(|m1, m2| f64::total_cmp(m1, m2))
+        .unwrap();
 
     dataset
         .iter()
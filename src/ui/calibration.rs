@@ -0,0 +1,193 @@
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Alignment, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Paragraph, StatefulWidget, Widget};
+
+use super::app_color;
+use super::utils;
+use super::{CALIBRATION_DETECTIONS, CALIBRATION_STABLE_RANGE};
+use crate::guitar::{
+    cents_difference, reference_pitch_for, semi_tone_down, semi_tone_up, Note, MAX_REFERENCE_PITCH,
+    MIN_REFERENCE_PITCH,
+};
+
+#[derive(Clone, Debug)]
+pub struct Calibration();
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct State {
+    // The note played by the reference instrument
+    pub note: Note,
+    // Frequencies of the last consecutive stable detections
+    pub detections: Vec<f64>,
+}
+
+impl StatefulWidget for Calibration {
+    type State = State;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let mut text = vec![
+            Line::styled(
+                format!("Play {} on the reference instrument", state.note),
+                Style::default()
+                    .fg(*app_color::TEXT_LIGHT)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Line::from(""),
+        ];
+
+        match state.average_frequency() {
+            Some(frequency) => text.extend([
+                Line::from(format!(
+                    "{:.2} Hz (A4 = {:.1} Hz)",
+                    frequency,
+                    reference_pitch_for(&state.note, frequency)
+                )),
+                Line::from(format!(
+                    "Stable detections: {}/{}",
+                    state.detections.len(),
+                    CALIBRATION_DETECTIONS
+                )),
+            ]),
+
+            None => text.push(Line::from("Listening...")),
+        }
+
+        text.extend([
+            Line::from(""),
+            Line::from("h/l: Change the reference note"),
+            Line::from("<esc>: Cancel"),
+        ]);
+
+        let mut rect = Rect {
+            width: area.width,
+            height: text.len() as u16,
+            x: 0,
+            y: 0,
+        };
+
+        utils::center_rect_in_container(&mut rect, &area);
+        let paragraph = Paragraph::new(text)
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(*app_color::TEXT_LIGHT));
+        paragraph.render(rect, buf);
+    }
+}
+
+impl Calibration {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl State {
+    pub fn new() -> Self {
+        Self {
+            note: Note::new("A4"),
+            detections: vec![],
+        }
+    }
+
+    pub fn next_note(&mut self) {
+        self.note = semi_tone_up(&self.note);
+        self.detections.clear();
+    }
+
+    pub fn prev_note(&mut self) {
+        self.note = semi_tone_down(&self.note);
+        self.detections.clear();
+    }
+
+    // Record a detected frequency of the reference note. Once there are enough stable
+    // detections, returns the reference pitch which makes the note read 0 cents
+    pub fn add_detection(&mut self, frequency: f64) -> Option<f64> {
+        // Frequencies which cannot be reached with a valid reference pitch are
+        // most likely another note
+        let reference_pitch = reference_pitch_for(&self.note, frequency);
+        if !(MIN_REFERENCE_PITCH..=MAX_REFERENCE_PITCH).contains(&reference_pitch) {
+            self.detections.clear();
+            return None;
+        }
+
+        if let Some(average) = self.average_frequency() {
+            if cents_difference(frequency, average).abs() > CALIBRATION_STABLE_RANGE {
+                self.detections.clear();
+            }
+        }
+
+        self.detections.push(frequency);
+
+        if self.detections.len() < CALIBRATION_DETECTIONS {
+            return None;
+        }
+
+        let average = self.average_frequency().unwrap();
+        self.detections.clear();
+
+        Some(reference_pitch_for(&self.note, average))
+    }
+
+    pub fn average_frequency(&self) -> Option<f64> {
+        if self.detections.is_empty() {
+            return None;
+        }
+
+        Some(self.detections.iter().sum::<f64>() / self.detections.len() as f64)
+    }
+}
+
+#[cfg(test)]
+mod add_detection_tests {
+    use super::*;
+
+    #[test]
+    fn averages_stable_detections() {
+        let mut state = State::new();
+
+        assert_eq!(state.add_detection(441.8), None);
+        assert_eq!(state.add_detection(442.1), None);
+        assert_eq!(state.add_detection(441.9), None);
+        let result = state.add_detection(442.2).unwrap();
+
+        assert!((result - 442.0).abs() < 1e-9);
+        assert!(state.detections.is_empty());
+    }
+
+    #[test]
+    fn unstable_detection_restarts() {
+        let mut state = State::new();
+
+        state.add_detection(442.0);
+        state.add_detection(442.0);
+        state.add_detection(436.0);
+
+        assert_eq!(state.detections, vec![436.0]);
+    }
+
+    #[test]
+    fn ignores_other_notes() {
+        let mut state = State::new();
+
+        state.add_detection(442.0);
+        state.add_detection(329.6);
+
+        assert!(state.detections.is_empty());
+    }
+
+    #[test]
+    fn other_reference_note() {
+        let mut state = State::new();
+        state.next_note();
+        state.next_note();
+        state.next_note();
+        assert_eq!(state.note, Note::new("C5"));
+
+        for _ in 0..CALIBRATION_DETECTIONS - 1 {
+            state.add_detection(514.0);
+        }
+        let result = state.add_detection(514.0).unwrap();
+
+        assert!((result - 432.2).abs() < 0.1);
+    }
+}
@@ -0,0 +1,65 @@
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Alignment, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Paragraph, StatefulWidget, Widget};
+
+use super::app_color;
+use super::utils;
+use crate::pitch_detector::PitchEstimate;
+
+#[derive(Clone, Debug)]
+pub struct ChromaticNote();
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct State {
+    pub estimate: Option<PitchEstimate>,
+}
+
+impl StatefulWidget for ChromaticNote {
+    type State = State;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let text = match state.estimate {
+            Some(estimate) => vec![
+                Line::styled(
+                    estimate.note.to_string(),
+                    Style::default()
+                        .fg(*app_color::TEXT_LIGHT)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Line::from(""),
+                Line::from(format!("{:.2} Hz", estimate.frequency)),
+                Line::from(format!("{:+.1} cents", estimate.cents)),
+                Line::from(format!("Clarity: {:.0}%", estimate.clarity * 100.0)),
+            ],
+
+            None => vec![Line::from("Play any note")],
+        };
+
+        let mut rect = Rect {
+            width: area.width,
+            height: text.len() as u16,
+            x: 0,
+            y: 0,
+        };
+
+        utils::center_rect_in_container(&mut rect, &area);
+        let paragraph = Paragraph::new(text)
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(*app_color::TEXT_LIGHT));
+        paragraph.render(rect, buf);
+    }
+}
+
+impl ChromaticNote {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl State {
+    pub fn new() -> Self {
+        Self { estimate: None }
+    }
+}
@@ -0,0 +1,164 @@
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Paragraph, StatefulWidget, Widget, Wrap};
+
+use super::app_color;
+use super::utils;
+
+// Lines around the list of devices: title, blank lines and key hints
+const EXTRA_LINES: usize = 6;
+
+#[derive(Clone, Debug)]
+pub struct DevicePicker();
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct State {
+    // Names of the input devices, empty when not recording from a device
+    pub devices: Vec<String>,
+    // The device being recorded
+    pub current: Option<String>,
+    // Index of the highlighted device, while the picker is open
+    pub selected: Option<usize>,
+    // Outcome of the last switch
+    pub message: Option<String>,
+}
+
+impl StatefulWidget for DevicePicker {
+    type State = State;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let text = match (state.selected, &state.message) {
+            (Some(selected), _) => {
+                // Long lists scroll to keep the highlighted device visible
+                let visible = (area.height as usize)
+                    .saturating_sub(EXTRA_LINES + 2)
+                    .max(1);
+                let first = (selected + 1).saturating_sub(visible);
+
+                let devices = state
+                    .devices
+                    .iter()
+                    .enumerate()
+                    .skip(first)
+                    .take(visible)
+                    .map(|(index, name)| {
+                        let current = if Some(name) == state.current.as_ref() {
+                            " (current)"
+                        } else {
+                            ""
+                        };
+
+                        if index == selected {
+                            Line::styled(
+                                format!("> {}{}", name, current),
+                                Style::default().add_modifier(Modifier::BOLD),
+                            )
+                        } else {
+                            Line::from(format!("  {}{}", name, current))
+                        }
+                    });
+
+                [
+                    Line::styled(
+                        "Select the input device",
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Line::from(""),
+                ]
+                .into_iter()
+                .chain(devices)
+                .chain([
+                    Line::from(""),
+                    Line::from("j/k: Select device"),
+                    Line::from("<enter>: Record from it"),
+                    Line::from("<esc>: Cancel"),
+                ])
+                .collect()
+            }
+
+            (None, Some(message)) => vec![Line::from(message.as_str())],
+            (None, None) => vec![],
+        };
+
+        let width = area.width.saturating_sub(4);
+        let mut rect = Rect {
+            width,
+            // Long device names wrap on multiple lines
+            height: text
+                .iter()
+                .map(|line| (line.width() as u16).max(1).div_ceil(width.max(1)))
+                .sum(),
+            x: 0,
+            y: 0,
+        };
+        rect.height = rect.height.min(area.height.saturating_sub(2));
+
+        utils::center_rect_in_container(&mut rect, &area);
+        let paragraph = Paragraph::new(text)
+            .wrap(Wrap { trim: false })
+            .style(Style::default().fg(*app_color::TEXT_LIGHT));
+        paragraph.render(rect, buf);
+    }
+}
+
+impl DevicePicker {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl State {
+    pub fn new() -> Self {
+        Self {
+            devices: vec![],
+            current: None,
+            selected: None,
+            message: None,
+        }
+    }
+
+    // Whether the widget has anything to show
+    pub fn is_active(&self) -> bool {
+        self.selected.is_some() || self.message.is_some()
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.selected.is_some()
+    }
+
+    // Highlight the current device first
+    pub fn open(&mut self) {
+        if self.devices.is_empty() {
+            self.message = Some("Not recording from an input device".to_string());
+            return;
+        }
+
+        let current = self
+            .devices
+            .iter()
+            .position(|name| Some(name) == self.current.as_ref());
+        self.selected = Some(current.unwrap_or(0));
+        self.message = None;
+    }
+
+    pub fn next_device(&mut self) {
+        if let Some(selected) = self.selected.as_mut() {
+            *selected = (*selected + 1) % self.devices.len();
+        }
+    }
+
+    pub fn prev_device(&mut self) {
+        if let Some(selected) = self.selected.as_mut() {
+            *selected = (*selected + self.devices.len() - 1) % self.devices.len();
+        }
+    }
+
+    // Close the picker, returning the highlighted device
+    pub fn close(&mut self) -> Option<String> {
+        self.selected
+            .take()
+            .and_then(|selected| self.devices.get(selected).cloned())
+    }
+}
@@ -29,6 +29,11 @@ impl Widget for Instruction {
             Line::from("  • h/l: Select notes"),
             Line::from("  • <esc>: Exit selection"),
             Line::from(""),
+            Line::from("c: Toggle chromatic mode"),
+            Line::from("+/-: Adjust reference pitch"),
+            Line::from("a: Calibrate from a reference note"),
+            Line::from("s: Save the tuning"),
+            Line::from("d: Select the input device"),
             Line::from("q: Quit"),
         ];
 
@@ -29,8 +29,8 @@ impl LoadingIcon {
     }
 }
 
-impl<'a> Into<Span<'a>> for LoadingIcon {
-    fn into(self) -> Span<'a> {
-        Span::from(self.char.to_string())
+impl<'a> From<LoadingIcon> for Span<'a> {
+    fn from(val: LoadingIcon) -> Self {
+        Span::from(val.char.to_string())
     }
 }
@@ -1,18 +1,25 @@
 use anyhow::Result;
-use itertools::Itertools;
 use ratatui::backend::CrosstermBackend;
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::style::Style;
 use ratatui::terminal::Terminal;
 use ratatui::widgets::{Block, BorderType, Borders};
-use std::mem::discriminant;
-use std::sync::mpsc::Receiver;
+use std::path::PathBuf;
+use std::sync::mpsc::{Receiver, Sender};
 
+use super::guitar::Tuning;
+use super::instrument::Instrument;
+use super::pitch_detector;
+use super::tuning_library;
 use super::AppEvent;
-use app_state::AppState;
+use app_state::{AppState, Mode};
 use audio_graph::AudioGraph;
+use calibration::Calibration;
+use chromatic_note::ChromaticNote;
+use device_picker::DevicePicker;
 use instructions::Instruction;
 use insufficient_size_notice::InsufficientSizeNotice;
+use save_tuning::SaveTuning;
 use tuning_bar::TuningBar;
 use tuning_notes::TuningNotes;
 use tuning_pegs::TuningPegs;
@@ -20,9 +27,13 @@ use tuning_pegs::TuningPegs;
 mod app_color;
 mod app_state;
 mod audio_graph;
+mod calibration;
+mod chromatic_note;
+mod device_picker;
 mod instructions;
 mod insufficient_size_notice;
 mod loading_icon;
+mod save_tuning;
 mod tuning_bar;
 mod tuning_notes;
 mod tuning_pegs;
@@ -33,16 +44,41 @@ pub static mut FRAME_COUNT: usize = 0;
 // In cents. 100 cents is 1 semitone
 pub const IN_TUNE_RANGE: f64 = 8.0;
 
+// In seconds, how long the pitch stays in the in tune range before a string is marked as tuned
+pub const IN_TUNE_DURATION: f64 = 1.0;
+
+// In Hz, how much the reference pitch changes with one key press
+pub const REFERENCE_PITCH_STEP: f64 = 1.0;
+
+// Number of consecutive stable detections averaged to calibrate the reference pitch
+pub const CALIBRATION_DETECTIONS: usize = 4;
+
+// In cents, how far detections can be from each other to be considered stable
+pub const CALIBRATION_STABLE_RANGE: f64 = 5.0;
+
+// Pitch estimates below this clarity are ignored
+pub const MIN_CLARITY: f64 = 0.7;
+
 pub const MIN_REQUIRED_WIDTH: u16 = 90;
 pub const MIN_REQUIRED_HEIGHT: u16 = 35;
 
-pub fn render(event_stream: Receiver<AppEvent>) -> Result<()> {
+#[allow(clippy::too_many_arguments)]
+pub fn render(
+    event_stream: Receiver<AppEvent>,
+    instrument: &Instrument,
+    tuning: &Tuning,
+    mut detector_config: pitch_detector::Config,
+    update_rate: u32,
+    detector_config_sender: Sender<pitch_detector::Config>,
+    device_sender: Sender<String>,
+    tuning_library_path: Option<PathBuf>,
+) -> Result<()> {
     // startup: Enable raw mode for the terminal, giving us fine control over user input
     crossterm::terminal::enable_raw_mode()?;
     crossterm::execute!(std::io::stderr(), crossterm::terminal::EnterAlternateScreen)?;
 
     let mut terminal = Terminal::new(CrosstermBackend::new(std::io::stderr()))?;
-    let mut app_state = AppState::new();
+    let mut app_state = AppState::new(instrument, tuning, detector_config, update_rate);
 
     // Main application loop
     loop {
@@ -68,10 +104,25 @@ pub fn render(event_stream: Receiver<AppEvent>) -> Result<()> {
                 frame_rect,
             );
 
+            let tuning_strings_title = match app_state.mode {
+                // Notes can be changed from the UI, only name the tuning while it's still a preset
+                Mode::Strings => match app_state
+                    .instrument
+                    .tuning_of(&app_state.tuning_notes.notes)
+                {
+                    Some(tuning) => {
+                        format!("Tuning strings: {}, {}", app_state.instrument, tuning)
+                    }
+                    None => format!("Tuning strings: {}", app_state.instrument),
+                },
+                Mode::Chromatic => "Chromatic tuner".to_string(),
+                Mode::Calibration => "Calibration".to_string(),
+            };
+
             f.render_widget(
                 Block::default()
                     .borders(Borders::ALL)
-                    .title("Tuning strings")
+                    .title(tuning_strings_title)
                     .border_type(BorderType::Rounded)
                     .border_style(Style::default().fg(*app_color::BORDER)),
                 tuning_strings_rect,
@@ -104,44 +155,100 @@ pub fn render(event_stream: Receiver<AppEvent>) -> Result<()> {
                 graph_rect,
             );
 
-            let layout = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints(vec![Constraint::Min(16), Constraint::Percentage(100)])
-                .split(tuning_strings_rect);
-
-            f.render_stateful_widget(
-                TuningNotes::new(),
-                utils::transform(layout[0], 2, 1),
-                &mut app_state.tuning_notes,
-            );
+            match app_state.mode {
+                Mode::Strings => {
+                    let layout = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints(vec![Constraint::Min(16), Constraint::Percentage(100)])
+                        .split(tuning_strings_rect);
+
+                    f.render_stateful_widget(
+                        TuningNotes::new(),
+                        utils::transform(layout[0], 2, 1),
+                        &mut app_state.tuning_notes,
+                    );
+
+                    f.render_stateful_widget(
+                        TuningPegs::new(),
+                        layout[1],
+                        &mut tuning_pegs::State {
+                            headstock: app_state.instrument.headstock,
+                            focus_pegs: current_pegs(&app_state),
+                        },
+                    );
+                }
+
+                Mode::Chromatic => f.render_stateful_widget(
+                    ChromaticNote::new(),
+                    tuning_strings_rect,
+                    &mut app_state.chromatic_note,
+                ),
+
+                Mode::Calibration => f.render_stateful_widget(
+                    Calibration::new(),
+                    tuning_strings_rect,
+                    &mut app_state.calibration,
+                ),
+            }
 
-            f.render_stateful_widget(
-                TuningPegs::new(),
-                layout[1],
-                &mut tuning_pegs::State {
-                    focus_peg: current_peg_index(&app_state),
-                },
-            );
             f.render_stateful_widget(TuningBar::new(), tuning_bar_rect, &mut app_state.tuning_bar);
 
-            f.render_widget(Instruction::new(), instructions_rect);
+            if app_state.save_tuning.is_active() {
+                f.render_stateful_widget(
+                    SaveTuning::new(),
+                    instructions_rect,
+                    &mut app_state.save_tuning,
+                );
+            } else if app_state.device_picker.is_active() {
+                f.render_stateful_widget(
+                    DevicePicker::new(),
+                    instructions_rect,
+                    &mut app_state.device_picker,
+                );
+            } else {
+                f.render_widget(Instruction::new(), instructions_rect);
+            }
+
             f.render_stateful_widget(AudioGraph::new(), graph_rect, &mut app_state.audio_graph);
         })?;
 
-        match poll_terminal_event()? {
+        match poll_terminal_event(app_state.save_tuning.name.is_some())? {
             Some(AppEvent::Quit) => break,
-            Some(event) => app_state.handle_event(&event),
+            Some(event) => {
+                // Messages stay until the next key press
+                app_state.save_tuning.message = None;
+                app_state.device_picker.message = None;
+                app_state.handle_event(&event);
+            }
             None => (),
         }
 
-        // Drop all events except for the last one
-        let events: Vec<AppEvent> = event_stream
-            .try_iter()
-            .unique_by(|x| discriminant(x))
-            .collect();
+        for event in latest_audio_only(event_stream.try_iter().collect()) {
+            app_state.handle_event(&event);
+        }
 
-        for event in events.iter() {
-            app_state.handle_event(event);
+        // Keep the detector in sync with config changes made from the UI
+        if app_state.detector_config != detector_config {
+            detector_config = app_state.detector_config;
+            // The detector thread may have stopped, the UI keeps working without it
+            let _ = detector_config_sender.send(detector_config);
+        }
+
+        if let Some(device) = app_state.device_to_select.take() {
+            let _ = device_sender.send(device);
+        }
+
+        if let Some(tuning) = app_state.tuning_to_save.take() {
+            let result = match tuning_library_path.as_ref() {
+                Some(path) => tuning_library::save(path, &tuning)
+                    .map(|_| format!("Saved \"{}\" to {}", tuning, path.display())),
+                None => Err(anyhow::anyhow!("Could not find the config directory")),
+            };
+
+            app_state.save_tuning.message = Some(match result {
+                Ok(message) => message,
+                Err(error) => format!("Failed to save \"{}\": {:#}", tuning, error),
+            });
         }
     }
 
@@ -152,7 +259,7 @@ pub fn render(event_stream: Receiver<AppEvent>) -> Result<()> {
     Ok(())
 }
 
-fn current_peg_index(state: &AppState) -> Option<usize> {
+fn current_pegs(state: &AppState) -> Vec<usize> {
     match state.tuning_notes.detecting_note {
         Some(note) => {
             let index = state
@@ -162,10 +269,10 @@ fn current_peg_index(state: &AppState) -> Option<usize> {
                 .position(|item| *item == note)
                 .unwrap();
 
-            Some(index)
+            state.instrument.pegs_of_course(index)
         }
 
-        None => None,
+        None => vec![],
     }
 }
 
@@ -194,18 +301,33 @@ fn calculate_layout(root_rect: Rect) -> [Rect; 4] {
         .constraints(vec![Constraint::Percentage(60), Constraint::Percentage(40)])
         .split(total_layout[0]);
 
-    return [
+    [
         upper_half[0],
         upper_half[1],
         total_layout[1],
         total_layout[2],
-    ];
+    ]
 }
 
-fn poll_terminal_event() -> Result<Option<AppEvent>> {
+// While typing text, keys are sent as they are instead of being commands
+fn poll_terminal_event(typing: bool) -> Result<Option<AppEvent>> {
     if crossterm::event::poll(std::time::Duration::from_millis(25))? {
         // If a key event occurs, handle it
         if let crossterm::event::Event::Key(key) = crossterm::event::read()? {
+            if key.kind == crossterm::event::KeyEventKind::Press && typing {
+                let event = match key.code {
+                    crossterm::event::KeyCode::Char(character) => {
+                        Some(AppEvent::CharacterTyped(character))
+                    }
+                    crossterm::event::KeyCode::Backspace => Some(AppEvent::BackspacePressed),
+                    crossterm::event::KeyCode::Enter => Some(AppEvent::EnterPressed),
+                    crossterm::event::KeyCode::Esc => Some(AppEvent::EscButtonPressed),
+                    _ => None,
+                };
+
+                return Ok(event);
+            }
+
             if key.kind == crossterm::event::KeyEventKind::Press {
                 let event = match key.code {
                     crossterm::event::KeyCode::Char('j') => Some(AppEvent::DownButtonPressed),
@@ -213,6 +335,16 @@ fn poll_terminal_event() -> Result<Option<AppEvent>> {
                     crossterm::event::KeyCode::Char('l') => Some(AppEvent::RightButtonPressed),
                     crossterm::event::KeyCode::Char('h') => Some(AppEvent::LeftButtonPressed),
                     crossterm::event::KeyCode::Esc => Some(AppEvent::EscButtonPressed),
+                    crossterm::event::KeyCode::Enter => Some(AppEvent::EnterPressed),
+                    crossterm::event::KeyCode::Char('c') => Some(AppEvent::ChromaticButtonPressed),
+                    crossterm::event::KeyCode::Char('a') => {
+                        Some(AppEvent::CalibrationButtonPressed)
+                    }
+                    crossterm::event::KeyCode::Char('+') => Some(AppEvent::PlusButtonPressed),
+                    crossterm::event::KeyCode::Char('=') => Some(AppEvent::PlusButtonPressed),
+                    crossterm::event::KeyCode::Char('-') => Some(AppEvent::MinusButtonPressed),
+                    crossterm::event::KeyCode::Char('s') => Some(AppEvent::SaveButtonPressed),
+                    crossterm::event::KeyCode::Char('d') => Some(AppEvent::DeviceButtonPressed),
                     crossterm::event::KeyCode::Char('q') => Some(AppEvent::Quit),
                     _ => None,
                 };
@@ -224,3 +356,40 @@ fn poll_terminal_event() -> Result<Option<AppEvent>> {
 
     Ok(None)
 }
+
+// The detector may have sent a few events since the last frame. The detection events are all
+// kept in order, the pitch tracker smooths over them. Only the latest recorded audio is drawn
+fn latest_audio_only(events: Vec<AppEvent>) -> Vec<AppEvent> {
+    let is_audio = |event: &AppEvent| matches!(event, AppEvent::AudioRecorded(_));
+    let latest_audio = events.iter().rposition(is_audio);
+
+    events
+        .into_iter()
+        .enumerate()
+        .filter(|(index, event)| !is_audio(event) || Some(*index) == latest_audio)
+        .map(|(_, event)| event)
+        .collect()
+}
+
+#[cfg(test)]
+mod latest_audio_only_tests {
+    use super::*;
+
+    #[test]
+    fn keeps_detection_events_in_order() {
+        let events = latest_audio_only(vec![
+            AppEvent::AudioRecorded(vec![1.0]),
+            AppEvent::PluckDetected,
+            AppEvent::NoPitchDetected,
+            AppEvent::AudioRecorded(vec![2.0]),
+            AppEvent::PluckDetected,
+            AppEvent::AudioRecorded(vec![3.0]),
+        ]);
+
+        assert_eq!(events.len(), 4);
+        assert!(matches!(events[0], AppEvent::PluckDetected));
+        assert!(matches!(events[1], AppEvent::NoPitchDetected));
+        assert!(matches!(events[2], AppEvent::PluckDetected));
+        assert!(matches!(&events[3], AppEvent::AudioRecorded(samples) if samples == &[3.0]));
+    }
+}
@@ -0,0 +1,87 @@
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Paragraph, StatefulWidget, Widget, Wrap};
+
+use super::app_color;
+use super::utils;
+
+#[derive(Clone, Debug)]
+pub struct SaveTuning();
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct State {
+    // The name being typed, while the prompt is open
+    pub name: Option<String>,
+    // Outcome of the last save
+    pub message: Option<String>,
+}
+
+impl StatefulWidget for SaveTuning {
+    type State = State;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let text = match (&state.name, &state.message) {
+            (Some(name), _) => vec![
+                Line::styled(
+                    "Save the current tuning",
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Line::from(""),
+                Line::from(vec![
+                    Span::from("Name: "),
+                    Span::styled(
+                        format!("{}_", name),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                ]),
+                Line::from(""),
+                Line::from("<enter>: Save"),
+                Line::from("<esc>: Cancel"),
+            ],
+
+            (None, Some(message)) => vec![Line::from(message.as_str())],
+            (None, None) => vec![],
+        };
+
+        let width = area.width.saturating_sub(4);
+        let mut rect = Rect {
+            width,
+            // Long messages, e.g. with file paths, wrap on multiple lines
+            height: text
+                .iter()
+                .map(|line| (line.width() as u16).max(1).div_ceil(width.max(1)))
+                .sum(),
+            x: 0,
+            y: 0,
+        };
+        rect.height = rect.height.min(area.height.saturating_sub(2));
+
+        utils::center_rect_in_container(&mut rect, &area);
+        let paragraph = Paragraph::new(text)
+            .wrap(Wrap { trim: false })
+            .style(Style::default().fg(*app_color::TEXT_LIGHT));
+        paragraph.render(rect, buf);
+    }
+}
+
+impl SaveTuning {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl State {
+    pub fn new() -> Self {
+        Self {
+            name: None,
+            message: None,
+        }
+    }
+
+    // Whether the widget has anything to show
+    pub fn is_active(&self) -> bool {
+        self.name.is_some() || self.message.is_some()
+    }
+}
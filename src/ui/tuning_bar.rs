@@ -7,6 +7,7 @@ use ratatui::widgets::{Block, BorderType, Borders, Paragraph, StatefulWidget, Wi
 
 use super::{app_color, utils, IN_TUNE_RANGE};
 use crate::guitar::{get_note_frequency, semi_tone_down, semi_tone_up, Note};
+use crate::pitch_detector::Level;
 
 #[derive(Clone, Debug)]
 pub struct TuningBar {}
@@ -18,7 +19,13 @@ pub struct State {
     pub center: f64,
     pub accept_range: (f64, f64),
     pub current_pitch: Option<f64>,
-    pub pitch_in_accept_range_once: bool,
+    // Detections in a row in the accept range
+    pub detections_in_accept_range: usize,
+    pub reference_pitch: f64,
+    // Level of the input while it's below the noise gate
+    pub silence: Option<Level>,
+    // A string was just plucked, its pitch isn't measured yet
+    pub plucked: bool,
 }
 
 impl StatefulWidget for TuningBar {
@@ -63,10 +70,12 @@ impl StatefulWidget for TuningBar {
             buf,
         );
 
-        render_accept_range(&state, &bar_area, buf);
-        render_current_pitch(&state, &bar_area, buf);
-        render_pitch_difference(&state, &bar_area, buf);
-        render_in_tune_text(&bar_area, buf);
+        render_accept_range(state, &bar_area, buf);
+        render_current_pitch(state, &bar_area, buf);
+        render_pitch_difference(state, &bar_area, buf);
+        render_waiting_for_input(state, &bar_area, buf);
+        render_plucked(state, &bar_area, buf);
+        render_in_tune_text(state, &bar_area, buf);
     }
 }
 
@@ -121,6 +130,10 @@ fn find_pitch_bucket(pitch: f64, state: &State, bar_area: &Rect) -> u16 {
 }
 
 fn render_pitch_difference(state: &State, bar_area: &Rect, buf: &mut Buffer) {
+    if state.plucked {
+        return;
+    }
+
     if let Some(pitch) = state.current_pitch {
         let mut rect = Rect {
             x: 0,
@@ -145,6 +158,57 @@ fn render_pitch_difference(state: &State, bar_area: &Rect, buf: &mut Buffer) {
     }
 }
 
+fn render_waiting_for_input(state: &State, bar_area: &Rect, buf: &mut Buffer) {
+    let (None, Some(level)) = (state.current_pitch, state.silence) else {
+        return;
+    };
+
+    let mut rect = Rect {
+        x: 0,
+        y: 0,
+        width: bar_area.width,
+        height: 1,
+    };
+
+    utils::center_rect_in_container(&mut rect, bar_area);
+    rect = utils::transform(rect, 0, -2);
+
+    // A muted input has no level at all
+    let text = if level.rms.is_finite() {
+        format!("Waiting for input ({:.0} dB)", level.rms)
+    } else {
+        "Waiting for input".to_string()
+    };
+
+    Paragraph::new(text)
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(*app_color::TEXT_LIGHT))
+        .render(rect, buf);
+}
+
+// Shown instead of the pitch difference until the attack of the pluck is over
+fn render_plucked(state: &State, bar_area: &Rect, buf: &mut Buffer) {
+    if !state.plucked {
+        return;
+    }
+
+    let mut rect = Rect {
+        x: 0,
+        y: 0,
+        width: bar_area.width,
+        height: 1,
+    };
+
+    utils::center_rect_in_container(&mut rect, bar_area);
+    rect = utils::transform(rect, 0, -2);
+
+    Paragraph::new("Plucked, measuring...")
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(*app_color::TEXT_LIGHT))
+        .add_modifier(Modifier::BOLD)
+        .render(rect, buf);
+}
+
 fn pitch_difference(state: &State, current: f64) -> String {
     let mut in_cents = if current > state.center {
         (current - state.center) / (state.max - state.center) * 100.0
@@ -163,12 +227,12 @@ fn pitch_difference(state: &State, current: f64) -> String {
     }
 }
 
-fn render_in_tune_text(bar_area: &Rect, buf: &mut Buffer) {
+fn render_in_tune_text(state: &State, bar_area: &Rect, buf: &mut Buffer) {
     let mut rect = Rect {
         x: 0,
         y: 0,
         width: bar_area.width,
-        height: 2,
+        height: 3,
     };
 
     utils::center_rect_in_container(&mut rect, bar_area);
@@ -183,6 +247,13 @@ fn render_in_tune_text(bar_area: &Rect, buf: &mut Buffer) {
             ),
         ]),
         Line::from("(100 cents = 1 semitone)"),
+        Line::from(vec![
+            Span::from("Reference pitch: "),
+            Span::styled(
+                format!("A4 = {:.1} Hz", state.reference_pitch),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+        ]),
     ];
 
     Paragraph::new(text)
@@ -197,10 +268,10 @@ impl TuningBar {
 }
 
 impl State {
-    pub fn new(note: &Note) -> Self {
-        let base_note = get_note_frequency(note);
-        let sharp = get_note_frequency(&semi_tone_up(note));
-        let flat = get_note_frequency(&semi_tone_down(note));
+    pub fn new(note: &Note, reference_pitch: f64) -> Self {
+        let base_note = get_note_frequency(note, reference_pitch);
+        let sharp = get_note_frequency(&semi_tone_up(note), reference_pitch);
+        let flat = get_note_frequency(&semi_tone_down(note), reference_pitch);
 
         let flat_cent = (base_note - flat) / 100.0;
         let sharp_cent = (base_note - flat) / 100.0;
@@ -214,7 +285,10 @@ impl State {
                 base_note - flat_cent * IN_TUNE_RANGE,
                 base_note + sharp_cent * IN_TUNE_RANGE,
             ),
-            pitch_in_accept_range_once: false,
+            detections_in_accept_range: 0,
+            reference_pitch,
+            silence: None,
+            plucked: false,
         }
     }
 
@@ -27,9 +27,16 @@ impl StatefulWidget for TuningNotes {
     type State = State;
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        // Instruments with many strings don't have room for the borders
+        let bordered = area.height as usize >= state.notes.len() * 3;
+
         for (index, tuning_note) in state.notes.iter().enumerate() {
             let mut surround_block = Block::default()
-                .borders(Borders::ALL)
+                .borders(if bordered {
+                    Borders::ALL
+                } else {
+                    Borders::NONE
+                })
                 .border_type(BorderType::Plain);
 
             let mut spans = vec![Span::from(tuning_note.to_string())];
@@ -73,7 +80,11 @@ impl StatefulWidget for TuningNotes {
                 .set_style(paragraph_style)
                 .alignment(Alignment::Center);
 
-            let item_height = 3;
+            let item_height = if bordered { 3 } else { 1 };
+            if (index as u16 + 1) * item_height > area.height {
+                break;
+            }
+
             let render_area = Rect {
                 x: area.x,
                 y: area.y + (index as u16) * item_height,
@@ -6,36 +6,66 @@ use ratatui::widgets::{Paragraph, StatefulWidget, Widget};
 
 use super::app_color;
 use super::utils;
+use crate::instrument::Headstock;
 
 #[derive(Clone, Debug)]
 pub struct TuningPegs();
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct State {
-    pub focus_peg: Option<usize>,
+    pub headstock: Headstock,
+    pub focus_pegs: Vec<usize>,
 }
 
+// Pegs are numbered like the strings: up the right side of the headstock, then down the
+// left side. For a 3+3 headstock:
+//   ┌────┐
+// 3 ││││││ 2
+//   ││││││
+// 4 ││││││ 1
+//   ││││││
+// 5 ││││││ 0
+//   └────┘
 impl StatefulWidget for TuningPegs {
     type State = State;
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
-        let text = vec![
-            Line::from("  ┌────┐ "),
-            Line::from(vec![peg(state, 3), Span::from(" ││││││ "), peg(state, 2)]),
-            Line::from("  ││││││  "),
-            Line::from(vec![peg(state, 4), Span::from(" ││││││ "), peg(state, 1)]),
-            Line::from("  ││││││  "),
-            Line::from(vec![peg(state, 5), Span::from(" ││││││ "), peg(state, 0)]),
-            Line::from("  └────┘  "),
-            Line::from("   │  │   "),
-            Line::from("   ├──┤   "),
-            Line::from("   │  │   "),
-            Line::from("   ├──┤   "),
-            Line::from("   │  │   "),
-        ];
+        let Headstock { left, right } = state.headstock;
+        let rows = left.max(right);
+        let width = (left + right).max(4);
+
+        let mut text = vec![Line::from(format!("  ┌{}┐  ", "─".repeat(width - 2)))];
+
+        for row in 0..rows {
+            if row > 0 {
+                text.push(Line::from(format!("  {}  ", "│".repeat(width))));
+            }
+
+            // Right pegs are aligned to the bottom, left pegs to the top
+            let right_peg = (row + right).checked_sub(rows).map(|i| right - 1 - i);
+            let left_peg = (row < left).then_some(right + row);
+
+            text.push(Line::from(vec![
+                peg(state, left_peg),
+                Span::from(format!(" {} ", "│".repeat(width))),
+                peg(state, right_peg),
+            ]));
+        }
+
+        text.push(Line::from(format!("  └{}┘  ", "─".repeat(width - 2))));
+
+        let neck = format!("   │{}│   ", " ".repeat(width - 4));
+        let fret = format!("   ├{}┤   ", "─".repeat(width - 4));
+        text.extend([
+            Line::from(neck.clone()),
+            Line::from(fret.clone()),
+            Line::from(neck.clone()),
+            Line::from(fret),
+            Line::from(neck),
+        ]);
 
         let mut rect = Rect {
-            width: 10,
+            width: width as u16 + 4,
             height: text.len() as u16,
             x: 0,
             y: 0,
@@ -47,10 +77,13 @@ impl StatefulWidget for TuningPegs {
     }
 }
 
-fn peg(state: &State, index: usize) -> Span {
-    match state.focus_peg {
-        Some(x) if x == index => Span::styled("⬤", Style::default().fg(*app_color::TEXT_LIGHT)),
-        _ => Span::from("◯"),
+fn peg(state: &State, index: Option<usize>) -> Span<'_> {
+    match index {
+        Some(x) if state.focus_pegs.contains(&x) => {
+            Span::styled("⬤", Style::default().fg(*app_color::TEXT_LIGHT))
+        }
+        Some(_) => Span::from("◯"),
+        None => Span::from(" "),
     }
 }
 
@@ -4,21 +4,17 @@ use ratatui::layout::{Constraint, Direction, Layout, Rect};
 
 #[allow(dead_code)]
 pub fn max_height(rect: Rect, max: u16) -> Rect {
-    let after = Layout::default()
+    Layout::default()
         .direction(Direction::Vertical)
         .constraints(vec![Constraint::Max(max), Constraint::Min(0)])
-        .split(rect)[0];
-
-    after
+        .split(rect)[0]
 }
 
 pub fn max_width(rect: Rect, max: u16) -> Rect {
-    let after = Layout::default()
+    Layout::default()
         .direction(Direction::Horizontal)
         .constraints(vec![Constraint::Max(max), Constraint::Min(0)])
-        .split(rect)[0];
-
-    after
+        .split(rect)[0]
 }
 
 pub fn transform(rect: Rect, x: i16, y: i16) -> Rect {